@@ -0,0 +1,301 @@
+use axum::{
+    Json, Router,
+    extract::{Extension, Path, Query, State},
+    http::HeaderMap,
+    middleware::from_fn_with_state,
+    response::Response,
+    routing::get,
+};
+use tracing::instrument;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState, auth,
+    error::{AppError, ErrorBody},
+    github::CollaboratorFetchOutcome,
+    handlers,
+    jobs::{Job, JobAccepted},
+    middleware,
+    models::{
+        Collaborator, CollaboratorPermission, DashboardQuery, DeployKeyRemoveRequest,
+        DeployKeyRemoveResponse, RemoveRequest, RemoveResponse, RepoDeployKeys,
+        RepoWithCollaborators, Repository, SessionData,
+    },
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Collaborator Audit API",
+        description = "Audit and remove collaborators on repositories owned by the authenticated GitHub user."
+    ),
+    components(schemas(ErrorBody)),
+    modifiers(&SecuritySchemes),
+    security(("bearer" = []), ("session" = [], "csrf" = []))
+)]
+struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(auth::SESSION_COOKIE))),
+        );
+        components.add_security_scheme(
+            "csrf",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-CSRF-Token"))),
+        );
+    }
+}
+
+/// JSON API mirroring the dashboard, plus its OpenAPI document at
+/// `/api/openapi.json`. The document is collected from the same route table, so
+/// it cannot drift from what is actually served.
+///
+/// Every `/api/v1` route goes through the same authentication and CSRF layers
+/// as the HTML routes, so it accepts either a session cookie with
+/// `X-CSRF-Token` or an `Authorization: Bearer` token.
+pub fn router(state: &AppState) -> Router<AppState> {
+    let v1 = OpenApiRouter::new()
+        .routes(routes!(list_repos))
+        .routes(routes!(audit))
+        .routes(routes!(repo_collaborators))
+        .routes(routes!(viewer_permission))
+        .routes(routes!(remove))
+        .routes(routes!(job_status))
+        .routes(routes!(deploy_keys))
+        .routes(routes!(remove_deploy_keys))
+        .layer(from_fn_with_state(state.clone(), middleware::csrf_protect))
+        .layer(from_fn_with_state(state.clone(), middleware::require_auth));
+
+    let (router, openapi) = OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .nest("/api/v1", v1)
+        .split_for_parts();
+
+    router.route(
+        "/api/openapi.json",
+        get(move || {
+            let openapi = openapi.clone();
+            async move { Json(openapi) }
+        }),
+    )
+}
+
+/// List repositories owned by the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/repos",
+    params(DashboardQuery),
+    responses(
+        (status = 200, body = [Repository]),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn list_repos(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Json<Vec<Repository>>, AppError> {
+    let repos = state
+        .github
+        .fetch_owned_repos(&session.access_token, &query.into())
+        .await?;
+    Ok(Json(repos))
+}
+
+/// List owned repositories with their external collaborators, as shown on the
+/// dashboard.
+#[utoipa::path(
+    get,
+    path = "/audit",
+    params(DashboardQuery),
+    responses(
+        (status = 200, body = [RepoWithCollaborators]),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn audit(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Json<Vec<RepoWithCollaborators>>, AppError> {
+    let rows = state
+        .github
+        .fetch_repos_with_collaborators(
+            &session.access_token,
+            &session.user_login,
+            query.into(),
+            state.config.max_concurrency,
+        )
+        .await?;
+    Ok(Json(rows))
+}
+
+/// List every collaborator of an owned repository.
+#[utoipa::path(
+    get,
+    path = "/repos/{repo}/collaborators",
+    params(("repo" = String, Path, description = "Repository name, owned by the authenticated user")),
+    responses(
+        (status = 200, body = [Collaborator]),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn repo_collaborators(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Path(repo): Path<String>,
+) -> Result<Json<Vec<Collaborator>>, AppError> {
+    match state
+        .github
+        .fetch_repo_collaborators(&session.access_token, &session.user_login, &repo)
+        .await?
+    {
+        CollaboratorFetchOutcome::Success(collaborators) => Ok(Json(collaborators)),
+        CollaboratorFetchOutcome::Forbidden => Err(AppError::Forbidden),
+    }
+}
+
+/// Effective permission of the authenticated user on an owned repository.
+#[utoipa::path(
+    get,
+    path = "/repos/{repo}/permission",
+    params(("repo" = String, Path, description = "Repository name, owned by the authenticated user")),
+    responses(
+        (status = 200, body = CollaboratorPermission),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn viewer_permission(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Path(repo): Path<String>,
+) -> Result<Json<CollaboratorPermission>, AppError> {
+    state
+        .github
+        .fetch_effective_permission(
+            &session.access_token,
+            &session.user_login,
+            &repo,
+            &session.user_login,
+        )
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("repository not found: {repo}")))
+}
+
+/// Remove collaborators in bulk. Each item is checked and reported on its own.
+/// With `background: true` the batch is queued and its progress is served at
+/// the returned `status_url`. A retry carrying the same `Idempotency-Key` and
+/// body gets the original response back instead of running the batch again.
+#[utoipa::path(
+    post,
+    path = "/remove",
+    request_body = RemoveRequest,
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Client-chosen key, 1 to 255 visible ASCII characters")
+    ),
+    responses(
+        (status = 200, body = RemoveResponse),
+        (status = 202, body = JobAccepted),
+        (status = 400, body = ErrorBody),
+        (status = 409, body = ErrorBody, description = "The original request with this key failed"),
+        (status = 422, body = ErrorBody, description = "Key reused with a different body"),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Missing or invalid CSRF token")
+    )
+)]
+#[instrument(skip_all)]
+pub async fn remove(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    headers: HeaderMap,
+    Json(payload): Json<RemoveRequest>,
+) -> Result<Response, AppError> {
+    handlers::remove_batch(&state, session, &headers, payload, "/api/v1/jobs").await
+}
+
+/// Progress and per-item results of a background removal job started by the
+/// authenticated user.
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(("id" = String, Path, description = "Job ID returned by `/remove`")),
+    responses(
+        (status = 200, body = Job),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn job_status(
+    state: State<AppState>,
+    session: Extension<SessionData>,
+    id: Path<String>,
+) -> Result<Json<Job>, AppError> {
+    handlers::job_status(state, session, id).await
+}
+
+/// Deploy keys of every owned repository, with whether each can push and
+/// when it was last used.
+#[utoipa::path(
+    get,
+    path = "/deploy-keys",
+    params(DashboardQuery),
+    responses(
+        (status = 200, body = [RepoDeployKeys]),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn deploy_keys(
+    state: State<AppState>,
+    session: Extension<SessionData>,
+    query: Query<DashboardQuery>,
+) -> Result<Json<Vec<RepoDeployKeys>>, AppError> {
+    handlers::deploy_keys(state, session, query).await
+}
+
+/// Delete deploy keys in bulk, with the ownership and admin checks of
+/// `/remove`. Each item is checked and reported on its own.
+#[utoipa::path(
+    post,
+    path = "/deploy-keys/remove",
+    request_body = DeployKeyRemoveRequest,
+    responses(
+        (status = 200, body = DeployKeyRemoveResponse),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Missing or invalid CSRF token")
+    )
+)]
+#[instrument(skip_all)]
+pub async fn remove_deploy_keys(
+    state: State<AppState>,
+    session: Extension<SessionData>,
+    payload: Json<DeployKeyRemoveRequest>,
+) -> Result<Json<DeployKeyRemoveResponse>, AppError> {
+    handlers::remove_deploy_keys(state, session, payload).await
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEvent {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub action: &'static str,
+    pub outcome: AuditOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Second user who signed off on the action, for approved proposals.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approver: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(actor: &str, action: &'static str, outcome: AuditOutcome) -> Self {
+        Self {
+            at: Utc::now(),
+            actor: actor.to_owned(),
+            action,
+            outcome,
+            detail: None,
+            approver: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_approver(mut self, approver: Option<&str>) -> Self {
+        self.approver = approver.map(str::to_owned);
+        self
+    }
+}
+
+/// Security-relevant actions, emitted on the `audit` tracing target and
+/// optionally appended as JSON lines to a file.
+#[derive(Clone, Default)]
+pub struct AuditTrail {
+    file: Option<Arc<Mutex<File>>>,
+}
+
+impl AuditTrail {
+    pub fn open(path: Option<&Path>) -> Result<Self, AppError> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                AppError::Config(format!("failed to open audit log {}: {e}", path.display()))
+            })?;
+
+        Ok(Self {
+            file: Some(Arc::new(Mutex::new(file))),
+        })
+    }
+
+    pub fn record(&self, event: AuditEvent) {
+        info!(
+            target: "audit",
+            actor = event.actor,
+            action = event.action,
+            outcome = ?event.outcome,
+            detail = event.detail.as_deref().unwrap_or_default(),
+            approver = event.approver.as_deref().unwrap_or_default(),
+            "audit event"
+        );
+
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let written = serde_json::to_string(&event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                let mut file = file.lock().map_err(|e| e.to_string())?;
+                writeln!(file, "{line}").map_err(|e| e.to_string())
+            });
+
+        if let Err(err) = written {
+            warn!(error = err, "failed to append audit event");
+        }
+    }
+}
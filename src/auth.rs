@@ -1,42 +1,51 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use axum_extra::extract::{
     PrivateCookieJar,
     cookie::{Cookie, SameSite},
 };
-use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use http::{HeaderMap, header::AUTHORIZATION};
+use sha2::{Digest, Sha256};
+use tracing::warn;
 
-use crate::{error::AppError, models::SessionData};
+use crate::{error::AppError, github::GitHubApi, models::SessionData, session::Sessions};
 
 pub const SESSION_COOKIE: &str = "gh_session";
 pub const OAUTH_STATE_COOKIE: &str = "gh_oauth_state";
 
-pub fn read_session(jar: &PrivateCookieJar) -> Result<Option<SessionData>, AppError> {
-    let Some(cookie) = jar.get(SESSION_COOKIE) else {
+/// How long a validated bearer token is trusted before GitHub is asked again.
+const TOKEN_SESSION_TTL: Duration = Duration::from_secs(5 * 60);
+
+pub fn session_id(jar: &PrivateCookieJar) -> Option<String> {
+    jar.get(SESSION_COOKIE)
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|id| !id.is_empty())
+}
+
+pub fn read_session(
+    jar: &PrivateCookieJar,
+    sessions: &Sessions,
+) -> Result<Option<SessionData>, AppError> {
+    let Some(id) = session_id(jar) else {
         return Ok(None);
     };
 
-    let decoded = URL_SAFE_NO_PAD
-        .decode(cookie.value())
-        .map_err(|_| AppError::Auth)?;
-    let session = serde_json::from_slice::<SessionData>(&decoded)?;
-    Ok(Some(session))
+    sessions.load(&id)
 }
 
-pub fn write_session(
-    jar: PrivateCookieJar,
-    session: &SessionData,
-    secure: bool,
-) -> Result<PrivateCookieJar, AppError> {
-    let json = serde_json::to_vec(session)?;
-    let encoded = URL_SAFE_NO_PAD.encode(json);
-
-    let cookie = Cookie::build((SESSION_COOKIE, encoded))
+pub fn write_session(jar: PrivateCookieJar, session_id: &str, secure: bool) -> PrivateCookieJar {
+    let cookie = Cookie::build((SESSION_COOKIE, session_id.to_owned()))
         .path("/")
         .http_only(true)
         .secure(secure)
         .same_site(SameSite::Lax)
         .build();
 
-    Ok(jar.add(cookie))
+    jar.add(cookie)
 }
 
 pub fn clear_session(jar: PrivateCookieJar, secure: bool) -> PrivateCookieJar {
@@ -74,12 +83,109 @@ pub fn clear_oauth_state(jar: PrivateCookieJar, secure: bool) -> PrivateCookieJa
     jar.remove(cookie)
 }
 
+pub const REQUIRED_SCOPES: [&str; 2] = ["repo", "read:org"];
+
 pub fn has_required_scopes(scopes: &str) -> bool {
+    missing_scopes(scopes).is_empty()
+}
+
+pub fn missing_scopes(scopes: &str) -> Vec<&'static str> {
     let normalized = scopes
         .split([',', ' '])
         .map(str::trim)
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>();
 
-    normalized.contains(&"repo") && normalized.contains(&"read:org")
+    REQUIRED_SCOPES
+        .into_iter()
+        .filter(|scope| !normalized.contains(scope))
+        .collect()
+}
+
+pub fn ensure_required_scopes(scopes: &str) -> Result<(), AppError> {
+    if has_required_scopes(scopes) {
+        return Ok(());
+    }
+
+    let granted = if scopes.trim().is_empty() {
+        "none"
+    } else {
+        scopes.trim()
+    };
+    Err(AppError::InsufficientScopes(format!(
+        "missing {}; granted: {granted}; required: {}",
+        missing_scopes(scopes).join(", "),
+        REQUIRED_SCOPES.join(", ")
+    )))
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// Builds a session for a personal access token supplied outside the OAuth flow.
+/// Classic tokens report their scopes in `x-oauth-scopes`; fine-grained tokens do
+/// not, so their permissions are only enforced by GitHub per request.
+pub async fn session_from_token(
+    github: &dyn GitHubApi,
+    token: &str,
+) -> Result<SessionData, AppError> {
+    let identity = github.fetch_token_identity(token).await?;
+
+    match identity.scopes.as_deref() {
+        Some(scopes) => ensure_required_scopes(scopes)?,
+        None => warn!(
+            user = identity.user.login,
+            "token does not report oauth scopes, skipping scope validation"
+        ),
+    }
+
+    Ok(SessionData {
+        access_token: token.to_owned(),
+        user_login: identity.user.login,
+        csrf_token: String::new(),
+        granted_scopes: identity.scopes,
+    })
+}
+
+/// Sessions built for bearer tokens, so a script making many calls does not
+/// cost a `GET /user` each. Keyed by a SHA-256 digest of the token; failed
+/// validations are not cached.
+#[derive(Clone, Default)]
+pub struct TokenSessions {
+    entries: Arc<Mutex<HashMap<Vec<u8>, TokenSession>>>,
+}
+
+/// A token's session and when GitHub last confirmed it.
+type TokenSession = (SessionData, Instant);
+
+impl TokenSessions {
+    pub async fn session(
+        &self,
+        github: &dyn GitHubApi,
+        token: &str,
+    ) -> Result<SessionData, AppError> {
+        let key = Sha256::digest(token.as_bytes()).to_vec();
+        if let Some((session, validated_at)) = self
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+            && validated_at.elapsed() < TOKEN_SESSION_TTL
+        {
+            return Ok(session.clone());
+        }
+
+        let session = session_from_token(github, token).await?;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (_, validated_at)| validated_at.elapsed() < TOKEN_SESSION_TTL);
+        entries.insert(key, (session.clone(), Instant::now()));
+        Ok(session)
+    }
 }
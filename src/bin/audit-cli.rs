@@ -0,0 +1,549 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use learning_rust::{
+    audit::AuditTrail,
+    auth,
+    config::AppConfig,
+    error::AppError,
+    github::{GitHubApi, GitHubClient},
+    models::{RemoveItem, RemoveRequest, RemoveResponse, RepoFilterOptions, SessionData},
+    notify::Notifier,
+    redact::{self, Redacting},
+    service::AuditService,
+};
+use serde::Serialize;
+use tracing::warn;
+use tracing_subscriber::EnvFilter;
+use url::Url;
+
+#[derive(Parser)]
+#[command(
+    name = "audit-cli",
+    about = "Audit and remove collaborators on repositories you own, from a terminal"
+)]
+struct Cli {
+    /// GitHub token with the `repo` and `read:org` scopes.
+    #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+    token: String,
+    /// REST API root, for GitHub Enterprise Server.
+    #[arg(long, env = "GITHUB_API_URL", default_value = "https://api.github.com")]
+    api_url: Url,
+    /// Maximum number of repositories fetched in parallel.
+    #[arg(long, default_value_t = 10)]
+    concurrency: usize,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List owned repositories with their external collaborators.
+    List {
+        #[command(flatten)]
+        filters: Filters,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Remove collaborators, either a single repo/user pair or a batch from a file.
+    Remove(RemoveArgs),
+    /// Summarize collaborator access across owned repositories.
+    Report {
+        #[command(flatten)]
+        filters: Filters,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+        format: ReportFormat,
+    },
+}
+
+#[derive(Args)]
+struct Filters {
+    #[arg(long)]
+    ignore_forks: bool,
+    #[arg(long)]
+    ignore_archived: bool,
+}
+
+impl From<&Filters> for RepoFilterOptions {
+    fn from(value: &Filters) -> Self {
+        Self {
+            ignore_forks: value.ignore_forks,
+            ignore_archived: value.ignore_archived,
+        }
+    }
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("target").required(true).args(["repo", "from_file"])))]
+struct RemoveArgs {
+    /// Repository name, owned by the token's user.
+    #[arg(long, requires = "user")]
+    repo: Option<String>,
+    /// Collaborator login to remove from `--repo`.
+    #[arg(long, requires = "repo")]
+    user: Option<String>,
+    /// CSV with `repo,username` columns, or JSON shaped like the `/remove` request body.
+    #[arg(long, conflicts_with_all = ["repo", "user"])]
+    from_file: Option<PathBuf>,
+    /// Skip the confirmation prompt.
+    #[arg(long)]
+    yes: bool,
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Table,
+    Json,
+}
+
+#[derive(Serialize)]
+struct ListRow {
+    repo: String,
+    collaborator: String,
+    permission: &'static str,
+    can_remove: bool,
+}
+
+#[derive(Serialize)]
+struct ResultRow<'a> {
+    repo: &'a str,
+    username: &'a str,
+    status: &'static str,
+    reason: &'a str,
+}
+
+#[derive(Serialize)]
+struct Report {
+    user: String,
+    repositories_with_collaborators: usize,
+    collaborator_assignments: usize,
+    removable_assignments: usize,
+    by_permission: BTreeMap<&'static str, usize>,
+    collaborators: Vec<CollaboratorSummary>,
+    unreadable_repositories: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct CollaboratorSummary {
+    login: String,
+    repositories: usize,
+    highest_permission: &'static str,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Logs go to stderr so table, JSON and CSV output on stdout stays clean.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .with_writer(Redacting(io::stderr))
+        .init();
+
+    let cli = Cli::parse();
+    redact::register_secret(&cli.token);
+    match run(cli).await {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<ExitCode, AppError> {
+    let github: Arc<dyn GitHubApi> = Arc::new(GitHubClient::new(&cli.api_url)?);
+    let session = auth::session_from_token(github.as_ref(), &cli.token).await?;
+
+    match cli.command {
+        Command::List { filters, format } => {
+            list(&github, &session, &filters, format, cli.concurrency).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Report { filters, format } => {
+            report(&github, &session, &filters, format, cli.concurrency).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Remove(args) => remove(&github, &session, args).await,
+    }
+}
+
+async fn list(
+    github: &Arc<dyn GitHubApi>,
+    session: &SessionData,
+    filters: &Filters,
+    format: Format,
+    concurrency: usize,
+) -> Result<(), AppError> {
+    let repos = github
+        .fetch_repos_with_collaborators(
+            &session.access_token,
+            &session.user_login,
+            filters.into(),
+            concurrency,
+        )
+        .await?;
+
+    let rows = repos
+        .into_iter()
+        .flat_map(|row| {
+            let repo = row.repo.name;
+            let can_remove = row.can_remove;
+            row.collaborators.into_iter().map(move |c| ListRow {
+                repo: repo.clone(),
+                permission: c.permission_label(),
+                collaborator: c.login,
+                can_remove,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Json => print_json(&rows),
+        Format::Csv => print_csv(&rows),
+        Format::Table => {
+            print_table(
+                &["REPOSITORY", "COLLABORATOR", "PERMISSION", "REMOVABLE"],
+                rows.iter()
+                    .map(|r| {
+                        vec![
+                            r.repo.clone(),
+                            r.collaborator.clone(),
+                            r.permission.to_string(),
+                            yes_no(r.can_remove).to_string(),
+                        ]
+                    })
+                    .collect(),
+            );
+            Ok(())
+        }
+    }
+}
+
+async fn report(
+    github: &Arc<dyn GitHubApi>,
+    session: &SessionData,
+    filters: &Filters,
+    format: ReportFormat,
+    concurrency: usize,
+) -> Result<(), AppError> {
+    let audit = github
+        .fetch_repo_audit(
+            &session.access_token,
+            &session.user_login,
+            filters.into(),
+            concurrency,
+        )
+        .await?;
+
+    let mut by_permission = BTreeMap::new();
+    let mut per_collaborator: HashMap<String, (usize, &'static str)> = HashMap::new();
+    let mut assignments = 0;
+    let mut removable = 0;
+
+    for row in &audit.rows {
+        for collaborator in &row.collaborators {
+            let label = collaborator.permission_label();
+            assignments += 1;
+            if row.can_remove {
+                removable += 1;
+            }
+            *by_permission.entry(label).or_insert(0) += 1;
+
+            let entry = per_collaborator
+                .entry(collaborator.login.clone())
+                .or_insert((0, label));
+            entry.0 += 1;
+            if permission_rank(label) > permission_rank(entry.1) {
+                entry.1 = label;
+            }
+        }
+    }
+
+    let mut collaborators = per_collaborator
+        .into_iter()
+        .map(
+            |(login, (repositories, highest_permission))| CollaboratorSummary {
+                login,
+                repositories,
+                highest_permission,
+            },
+        )
+        .collect::<Vec<_>>();
+    collaborators.sort_by(|a, b| {
+        b.repositories
+            .cmp(&a.repositories)
+            .then_with(|| a.login.cmp(&b.login))
+    });
+
+    let report = Report {
+        user: session.user_login.clone(),
+        repositories_with_collaborators: audit.rows.len(),
+        collaborator_assignments: assignments,
+        removable_assignments: removable,
+        by_permission,
+        collaborators,
+        unreadable_repositories: audit.forbidden.into_iter().map(|r| r.name).collect(),
+    };
+
+    match format {
+        ReportFormat::Json => print_json(&report),
+        ReportFormat::Table => {
+            println!("Collaborator report for {}", report.user);
+            println!(
+                "  repositories with external collaborators: {}",
+                report.repositories_with_collaborators
+            );
+            println!(
+                "  collaborator assignments: {} ({} removable)",
+                report.collaborator_assignments, report.removable_assignments
+            );
+            for (permission, count) in &report.by_permission {
+                println!("    {permission}: {count}");
+            }
+            if !report.unreadable_repositories.is_empty() {
+                println!(
+                    "  repositories with unreadable collaborators: {}",
+                    report.unreadable_repositories.join(", ")
+                );
+            }
+            println!();
+            print_table(
+                &["COLLABORATOR", "REPOSITORIES", "HIGHEST PERMISSION"],
+                report
+                    .collaborators
+                    .iter()
+                    .map(|c| {
+                        vec![
+                            c.login.clone(),
+                            c.repositories.to_string(),
+                            c.highest_permission.to_string(),
+                        ]
+                    })
+                    .collect(),
+            );
+            Ok(())
+        }
+    }
+}
+
+async fn remove(
+    github: &Arc<dyn GitHubApi>,
+    session: &SessionData,
+    args: RemoveArgs,
+) -> Result<ExitCode, AppError> {
+    let items = match (&args.from_file, args.repo, args.user) {
+        (Some(path), _, _) => read_items(path)?,
+        (None, Some(repo), Some(username)) => vec![RemoveItem { repo, username }],
+        _ => {
+            return Err(AppError::BadRequest(
+                "provide --repo and --user, or --from-file".to_string(),
+            ));
+        }
+    };
+
+    if items.is_empty() {
+        return Err(AppError::BadRequest("items must not be empty".to_string()));
+    }
+
+    if !args.yes && !confirm(&items)? {
+        eprintln!("aborted, nothing was removed");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let service = removal_service(github, session)?;
+    let response = service.remove(session, items).await?;
+    print_remove_response(&response, args.format)?;
+
+    Ok(if response.failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// The server's removal service, so removals from a terminal get the same
+/// allowlist, removal policy, pacing, webhooks and audit log as the web UI.
+fn removal_service(
+    github: &Arc<dyn GitHubApi>,
+    session: &SessionData,
+) -> Result<AuditService, AppError> {
+    let config = AppConfig::load().map_err(|err| match err {
+        AppError::Config(message) => AppError::Config(format!(
+            "remove applies the server's removal policy and audit log, so it needs the server configuration: {message}"
+        )),
+        other => other,
+    })?;
+    if !config.is_user_allowed(&session.user_login) {
+        warn!(user = session.user_login, "user is not on the allowlist");
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(AuditService::new(
+        github.clone(),
+        AuditTrail::open(config.audit_log_path.as_deref())?,
+    )
+    .with_concurrency(config.max_concurrency)
+    .with_mutation_interval(config.mutation_interval)
+    .with_policy(config.removal_policy)
+    .with_notifier(Notifier::new(config.notify_webhooks)))
+}
+
+fn read_items(path: &Path) -> Result<Vec<RemoveItem>, AppError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| AppError::BadRequest(format!("failed to read {}: {e}", path.display())))?;
+
+    if path.extension().is_some_and(|ext| ext == "json") {
+        let request = serde_json::from_str::<RemoveRequest>(&content).map_err(|e| {
+            AppError::BadRequest(format!("invalid JSON in {}: {e}", path.display()))
+        })?;
+        return Ok(request.items);
+    }
+
+    csv::Reader::from_reader(content.as_bytes())
+        .deserialize::<RemoveItem>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::BadRequest(format!("invalid CSV in {}: {e}", path.display())))
+}
+
+fn confirm(items: &[RemoveItem]) -> Result<bool, AppError> {
+    if !io::stdin().is_terminal() {
+        return Err(AppError::BadRequest(
+            "refusing to remove without confirmation; pass --yes when not running interactively"
+                .to_string(),
+        ));
+    }
+
+    eprintln!(
+        "About to remove {} collaborator assignment(s):",
+        items.len()
+    );
+    for item in items {
+        eprintln!("  {} from {}", item.username, item.repo);
+    }
+    eprint!("This cannot be undone. Type 'yes' to continue: ");
+    io::stderr().flush().map_err(|_| AppError::Internal)?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|_| AppError::Internal)?;
+    Ok(answer.trim() == "yes")
+}
+
+fn print_remove_response(response: &RemoveResponse, format: Format) -> Result<(), AppError> {
+    if let Format::Json = format {
+        return print_json(response);
+    }
+
+    let rows = response
+        .success
+        .iter()
+        .map(|s| ResultRow {
+            repo: &s.repo,
+            username: &s.username,
+            status: "removed",
+            reason: "",
+        })
+        .chain(response.failed.iter().map(|f| ResultRow {
+            repo: &f.repo,
+            username: &f.username,
+            status: "failed",
+            reason: &f.reason,
+        }))
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Csv => print_csv(&rows),
+        _ => {
+            print_table(
+                &["REPOSITORY", "USERNAME", "STATUS", "REASON"],
+                rows.iter()
+                    .map(|r| {
+                        vec![
+                            r.repo.to_string(),
+                            r.username.to_string(),
+                            r.status.to_string(),
+                            r.reason.to_string(),
+                        ]
+                    })
+                    .collect(),
+            );
+            eprintln!(
+                "Removed {}. Failed {}.",
+                response.success.len(),
+                response.failed.len()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), AppError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_csv<T: Serialize>(rows: &[T]) -> Result<(), AppError> {
+    let mut writer = csv::Writer::from_writer(io::stdout());
+    for row in rows {
+        writer.serialize(row).map_err(|_| AppError::Internal)?;
+    }
+    writer.flush().map_err(|_| AppError::Internal)
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths = headers.iter().map(|h| h.len()).collect::<Vec<_>>();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let render = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", render(headers.to_vec()));
+    for row in &rows {
+        println!("{}", render(row.iter().map(String::as_str).collect()));
+    }
+}
+
+fn permission_rank(label: &str) -> u8 {
+    match label {
+        "admin" => 4,
+        "maintain" => 3,
+        "write" => 2,
+        "triage" => 1,
+        _ => 0,
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
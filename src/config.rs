@@ -0,0 +1,719 @@
+use std::{
+    env, fmt, fs,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    error::AppError,
+    github::RetryPolicy,
+    service::{DEFAULT_MUTATION_INTERVAL, RemovalPolicy},
+    workspaces::Workspace,
+};
+
+/// Env var naming the TOML config file. Without it only env vars are read.
+pub const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+
+#[derive(Clone)]
+pub struct AppConfig {
+    pub github_client_id: String,
+    pub github_client_secret: String,
+    pub session_secret: String,
+    pub base_url: Url,
+    /// Address the server listens on.
+    pub bind_addr: SocketAddr,
+    /// How long removal batches may keep running after a shutdown signal.
+    pub drain_timeout: Duration,
+    /// REST API root, `https://api.github.com` unless pointed at GHES or a stub.
+    pub github_api_url: Url,
+    /// Host of the OAuth authorize and token endpoints.
+    pub github_web_url: Url,
+    pub max_concurrency: usize,
+    /// Gap between collaborator DELETEs, for GitHub's secondary rate limits.
+    pub mutation_interval: Duration,
+    pub retry: RetryPolicy,
+    pub github_app: Option<GitHubAppConfig>,
+    pub github_token: Option<String>,
+    pub session_store: SessionStoreConfig,
+    pub session_idle_timeout: Duration,
+    pub session_absolute_timeout: Duration,
+    pub revoke_token_on_logout: bool,
+    /// Serve Prometheus metrics, unauthenticated, at `/metrics`.
+    pub metrics_enabled: bool,
+    pub audit_log_path: Option<PathBuf>,
+    /// Serve this fixture through `FakeGitHub` instead of talking to GitHub.
+    pub demo_fixture: Option<PathBuf>,
+    /// GitHub logins allowed to sign in; everyone when empty.
+    pub allowed_users: Vec<String>,
+    pub removal_policy: RemovalPolicy,
+    /// Background removal jobs allowed to run at once.
+    pub max_running_jobs: usize,
+    /// Webhooks notified after every removal batch.
+    pub notify_webhooks: Vec<Url>,
+    /// Shared audit scopes with app-level roles; config file only.
+    pub workspaces: Vec<Workspace>,
+}
+
+#[derive(Clone)]
+pub enum SessionStoreConfig {
+    Memory,
+    Sqlite(PathBuf),
+}
+
+#[derive(Clone)]
+pub struct GitHubAppConfig {
+    pub app_id: u64,
+    pub private_key_pem: String,
+}
+
+impl AppConfig {
+    /// Reads the file named by `CONFIG_FILE`, if any, with process env vars
+    /// taking precedence over it.
+    pub fn load() -> Result<Self, AppError> {
+        let path = env::var_os(CONFIG_FILE_ENV).map(PathBuf::from);
+        Self::from_sources(path.as_deref(), |key| env::var(key).ok())
+    }
+
+    /// Layers defaults, then the TOML file at `path`, then `env`. Errors name
+    /// the env var or file key that holds the offending value.
+    pub fn from_sources(
+        path: Option<&Path>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, AppError> {
+        let file = match path {
+            Some(path) => read_file(path)?,
+            None => FileConfig::default(),
+        };
+        let layers = Layers { path, env: &env };
+        let FileConfig {
+            server,
+            github,
+            session,
+            audit,
+            metrics,
+            demo,
+            access,
+            policy,
+            jobs,
+            notifications,
+            workspaces,
+        } = file;
+
+        let demo_fixture = layers
+            .get("DEMO_FIXTURE_PATH", "demo.fixture", demo.fixture)?
+            .map(|s| s.value);
+        let client_id = layers.get("GITHUB_CLIENT_ID", "github.client_id", github.client_id)?;
+        let client_secret = layers.get(
+            "GITHUB_CLIENT_SECRET",
+            "github.client_secret",
+            github.client_secret,
+        )?;
+        let github_token = layers.get("GITHUB_TOKEN", "github.token", github.token)?;
+        let github_app = GitHubAppConfig::from_layers(&layers, github.app)?;
+
+        let (github_client_id, github_client_secret) = match demo_fixture {
+            // Demo data must never mix with a real account, so any credential
+            // that would reach GitHub is refused rather than ignored.
+            Some(_) => {
+                let real = [
+                    client_id.as_ref().map(|s| s.source.to_string()),
+                    client_secret.as_ref().map(|s| s.source.to_string()),
+                    github_token.as_ref().map(|s| s.source.to_string()),
+                    github_app.as_ref().map(|(_, source)| source.to_string()),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+                if !real.is_empty() {
+                    return Err(AppError::Config(format!(
+                        "demo mode cannot be combined with GitHub credentials: {}",
+                        real.join(", ")
+                    )));
+                }
+                (String::new(), String::new())
+            }
+            None => (
+                required(client_id, "GITHUB_CLIENT_ID", "github.client_id")?,
+                required(
+                    client_secret,
+                    "GITHUB_CLIENT_SECRET",
+                    "github.client_secret",
+                )?,
+            ),
+        };
+        let session_secret = required(
+            layers.get("SESSION_SECRET", "session.secret", session.secret)?,
+            "SESSION_SECRET",
+            "session.secret",
+        )?;
+        let base_url = layers
+            .url("BASE_URL", "server.base_url", server.base_url)?
+            .ok_or_else(|| missing("BASE_URL", "server.base_url"))?;
+
+        let bind = layers.get("BIND_ADDRESS", "server.bind", server.bind)?;
+        let port = layers.get("PORT", "server.port", server.port)?;
+        if let Some(port) = &port
+            && port.value == 0
+        {
+            return Err(port.invalid("must be between 1 and 65535"));
+        }
+        let bind_addr = SocketAddr::new(
+            bind.map_or(IpAddr::from([0, 0, 0, 0]), |s| s.value),
+            port.map_or(3000, |s| s.value),
+        );
+
+        let github_api_url = layers
+            .url("GITHUB_API_URL", "github.api_url", github.api_url)?
+            .unwrap_or_else(|| Url::parse("https://api.github.com").expect("valid default"));
+        let github_web_url = layers
+            .url("GITHUB_WEB_URL", "github.web_url", github.web_url)?
+            .unwrap_or_else(|| Url::parse("https://github.com").expect("valid default"));
+
+        let max_concurrency = layers
+            .positive(
+                "MAX_CONCURRENCY",
+                "github.max_concurrency",
+                github.max_concurrency,
+            )?
+            .unwrap_or(10);
+        let mutation_interval = layers
+            .get(
+                "GITHUB_MUTATION_INTERVAL_MS",
+                "github.mutation_interval_ms",
+                github.mutation_interval_ms,
+            )?
+            .map_or(DEFAULT_MUTATION_INTERVAL, |s| {
+                Duration::from_millis(s.value)
+            });
+
+        let retry = {
+            let defaults = RetryPolicy::default();
+            let retry = github.retry;
+            RetryPolicy {
+                max_attempts: layers
+                    .positive(
+                        "GITHUB_RETRY_MAX_ATTEMPTS",
+                        "github.retry.max_attempts",
+                        retry.max_attempts,
+                    )?
+                    .unwrap_or(defaults.max_attempts),
+                default_backoff: layers
+                    .get(
+                        "GITHUB_RETRY_DEFAULT_BACKOFF_SECS",
+                        "github.retry.default_backoff_secs",
+                        retry.default_backoff_secs,
+                    )?
+                    .map_or(defaults.default_backoff, |s| Duration::from_secs(s.value)),
+                max_backoff: layers
+                    .positive(
+                        "GITHUB_RETRY_MAX_BACKOFF_SECS",
+                        "github.retry.max_backoff_secs",
+                        retry.max_backoff_secs,
+                    )?
+                    .map_or(defaults.max_backoff, Duration::from_secs),
+            }
+        };
+
+        let session_store = match layers.get("SESSION_STORE", "session.store", session.store)? {
+            None => SessionStoreConfig::Memory,
+            Some(store) => match store.value.as_str() {
+                "memory" => SessionStoreConfig::Memory,
+                "sqlite" => SessionStoreConfig::Sqlite(
+                    layers
+                        .get("SESSION_DB_PATH", "session.db_path", session.db_path)?
+                        .map_or_else(|| PathBuf::from("sessions.db"), |s| s.value),
+                ),
+                other => {
+                    return Err(store.invalid(format!("{other} (expected memory or sqlite)")));
+                }
+            },
+        };
+
+        let max_batch_size = layers.positive(
+            "MAX_REMOVAL_BATCH_SIZE",
+            "policy.max_batch_size",
+            policy.max_batch_size,
+        )?;
+
+        let notify_webhooks = match layers.list(
+            "NOTIFY_WEBHOOKS",
+            "notifications.webhooks",
+            notifications.webhooks,
+        ) {
+            Some(webhooks) => webhooks
+                .value
+                .iter()
+                .enumerate()
+                .map(|(i, raw)| {
+                    let url = Url::parse(raw).map_err(|e| webhooks.invalid_item(i, e))?;
+                    match url.scheme() {
+                        "http" | "https" => Ok(url),
+                        other => Err(webhooks.invalid_item(
+                            i,
+                            format!("unsupported scheme {other} (expected http or https)"),
+                        )),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        for (i, workspace) in workspaces.iter().enumerate() {
+            let invalid = |message: &str| {
+                AppError::Config(format!(
+                    "invalid workspaces[{i}] in {}: {message}",
+                    path.unwrap_or(Path::new(CONFIG_FILE_ENV)).display()
+                ))
+            };
+            if workspace.name.trim().is_empty() || workspace.org.trim().is_empty() {
+                return Err(invalid("name and org must be non-empty"));
+            }
+            if workspaces[..i].iter().any(|w| w.name == workspace.name) {
+                return Err(invalid(&format!("duplicate name {}", workspace.name)));
+            }
+        }
+
+        Ok(Self {
+            github_client_id,
+            github_client_secret,
+            session_secret,
+            base_url,
+            bind_addr,
+            drain_timeout: layers
+                .get(
+                    "SHUTDOWN_DRAIN_TIMEOUT_SECS",
+                    "server.drain_timeout_secs",
+                    server.drain_timeout_secs,
+                )?
+                .map_or(Duration::from_secs(30), |s| Duration::from_secs(s.value)),
+            github_api_url,
+            github_web_url,
+            max_concurrency,
+            mutation_interval,
+            retry,
+            github_app: github_app.map(|(app, _)| app),
+            github_token: github_token.map(|s| s.value),
+            session_store,
+            session_idle_timeout: layers
+                .get(
+                    "SESSION_IDLE_TIMEOUT_SECS",
+                    "session.idle_timeout_secs",
+                    session.idle_timeout_secs,
+                )?
+                .map_or(Duration::from_secs(8 * 60 * 60), |s| {
+                    Duration::from_secs(s.value)
+                }),
+            session_absolute_timeout: layers
+                .get(
+                    "SESSION_ABSOLUTE_TIMEOUT_SECS",
+                    "session.absolute_timeout_secs",
+                    session.absolute_timeout_secs,
+                )?
+                .map_or(Duration::from_secs(7 * 24 * 60 * 60), |s| {
+                    Duration::from_secs(s.value)
+                }),
+            revoke_token_on_logout: layers
+                .get(
+                    "REVOKE_TOKEN_ON_LOGOUT",
+                    "session.revoke_token_on_logout",
+                    session.revoke_token_on_logout.map(Flag),
+                )?
+                .is_some_and(|s| s.value.0),
+            metrics_enabled: layers
+                .get(
+                    "METRICS_ENABLED",
+                    "metrics.enabled",
+                    metrics.enabled.map(Flag),
+                )?
+                .is_some_and(|s| s.value.0),
+            audit_log_path: layers
+                .get("AUDIT_LOG_PATH", "audit.log_path", audit.log_path)?
+                .map(|s| s.value),
+            demo_fixture,
+            allowed_users: layers
+                .list(
+                    "ALLOWED_USERS",
+                    "access.allowed_users",
+                    access.allowed_users,
+                )
+                .map(|s| s.value)
+                .unwrap_or_default(),
+            removal_policy: RemovalPolicy {
+                protected_collaborators: layers
+                    .list(
+                        "PROTECTED_COLLABORATORS",
+                        "policy.protected_collaborators",
+                        policy.protected_collaborators,
+                    )
+                    .map(|s| s.value)
+                    .unwrap_or_default(),
+                max_batch_size,
+                approval_required_repos: layers
+                    .list(
+                        "APPROVAL_REQUIRED_REPOS",
+                        "policy.approval_required_repos",
+                        policy.approval_required_repos,
+                    )
+                    .map(|s| s.value)
+                    .unwrap_or_default(),
+            },
+            max_running_jobs: layers
+                .positive("MAX_RUNNING_JOBS", "jobs.max_running", jobs.max_running)?
+                .unwrap_or(2),
+            notify_webhooks,
+            workspaces,
+        })
+    }
+
+    /// Whether `login` may sign in. GitHub logins are case-insensitive.
+    pub fn is_user_allowed(&self, login: &str) -> bool {
+        self.allowed_users.is_empty()
+            || self
+                .allowed_users
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(login))
+    }
+
+    pub fn workspace(&self, name: &str) -> Option<&Workspace> {
+        self.workspaces.iter().find(|w| w.name == name)
+    }
+}
+
+impl GitHubAppConfig {
+    fn from_layers<'a>(
+        layers: &Layers<'a>,
+        app: AppSection,
+    ) -> Result<Option<(Self, Source<'a>)>, AppError> {
+        let Some(app_id) = layers.get("GITHUB_APP_ID", "github.app.id", app.id)? else {
+            return Ok(None);
+        };
+
+        let private_key_pem = match layers.get(
+            "GITHUB_APP_PRIVATE_KEY",
+            "github.app.private_key",
+            app.private_key,
+        )? {
+            // Keys passed inline through env files usually have escaped newlines.
+            Some(inline) => inline.value.replace("\\n", "\n"),
+            None => {
+                let path = required(
+                    layers.get(
+                        "GITHUB_APP_PRIVATE_KEY_PATH",
+                        "github.app.private_key_path",
+                        app.private_key_path,
+                    )?,
+                    "GITHUB_APP_PRIVATE_KEY_PATH",
+                    "github.app.private_key_path",
+                )?;
+                fs::read_to_string(&path).map_err(|e| {
+                    AppError::Config(format!("failed to read {}: {e}", path.display()))
+                })?
+            }
+        };
+
+        Ok(Some((
+            Self {
+                app_id: app_id.value,
+                private_key_pem,
+            },
+            app_id.source,
+        )))
+    }
+}
+
+/// Shape of the TOML config file. Every key is optional and unknown keys are
+/// rejected, so a typo fails loudly instead of silently using a default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: ServerSection,
+    github: GitHubSection,
+    session: SessionSection,
+    audit: AuditSection,
+    metrics: MetricsSection,
+    demo: DemoSection,
+    access: AccessSection,
+    policy: PolicySection,
+    jobs: JobsSection,
+    notifications: NotificationsSection,
+    workspaces: Vec<Workspace>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerSection {
+    bind: Option<IpAddr>,
+    port: Option<u16>,
+    base_url: Option<String>,
+    drain_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GitHubSection {
+    api_url: Option<String>,
+    web_url: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    token: Option<String>,
+    max_concurrency: Option<usize>,
+    mutation_interval_ms: Option<u64>,
+    retry: RetrySection,
+    app: AppSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RetrySection {
+    max_attempts: Option<u8>,
+    default_backoff_secs: Option<u64>,
+    max_backoff_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AppSection {
+    id: Option<u64>,
+    private_key: Option<String>,
+    private_key_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SessionSection {
+    secret: Option<String>,
+    store: Option<String>,
+    db_path: Option<PathBuf>,
+    idle_timeout_secs: Option<u64>,
+    absolute_timeout_secs: Option<u64>,
+    revoke_token_on_logout: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuditSection {
+    log_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MetricsSection {
+    enabled: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DemoSection {
+    fixture: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AccessSection {
+    allowed_users: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicySection {
+    protected_collaborators: Option<Vec<String>>,
+    max_batch_size: Option<usize>,
+    approval_required_repos: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct JobsSection {
+    max_running: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NotificationsSection {
+    webhooks: Option<Vec<String>>,
+}
+
+fn read_file(path: &Path) -> Result<FileConfig, AppError> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        AppError::Config(format!(
+            "failed to read config file {}: {e}",
+            path.display()
+        ))
+    })?;
+    let deserializer = toml::Deserializer::parse(&content).map_err(|e| {
+        AppError::Config(format!(
+            "invalid config file {}: {}",
+            path.display(),
+            e.message()
+        ))
+    })?;
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let key = e.path().to_string();
+        let message = e.inner().message();
+        AppError::Config(if key == "." {
+            format!("invalid config file {}: {message}", path.display())
+        } else {
+            format!("invalid {key} in {}: {message}", path.display())
+        })
+    })
+}
+
+/// Where a setting was read from, displayed as the env var or file key to fix.
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Env(&'static str),
+    File(&'static str, &'a Path),
+}
+
+impl fmt::Display for Source<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env(key) => f.write_str(key),
+            Self::File(key, path) => write!(f, "{key} in {}", path.display()),
+        }
+    }
+}
+
+struct Setting<'a, T> {
+    value: T,
+    source: Source<'a>,
+}
+
+impl<T> Setting<'_, T> {
+    fn invalid(&self, reason: impl fmt::Display) -> AppError {
+        AppError::Config(format!("invalid {}: {reason}", self.source))
+    }
+}
+
+impl Setting<'_, Vec<String>> {
+    fn invalid_item(&self, index: usize, reason: impl fmt::Display) -> AppError {
+        match self.source {
+            Source::Env(key) => AppError::Config(format!("invalid {key} entry {index}: {reason}")),
+            Source::File(key, path) => AppError::Config(format!(
+                "invalid {key}[{index}] in {}: {reason}",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// `true`/`false` that also accepts `1`/`0` and `yes`/`no` from env vars.
+struct Flag(bool);
+
+impl FromStr for Flag {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "1" | "true" | "yes" => Ok(Self(true)),
+            "0" | "false" | "no" => Ok(Self(false)),
+            other => Err(format!("{other} (expected true or false)")),
+        }
+    }
+}
+
+struct Layers<'a> {
+    path: Option<&'a Path>,
+    env: &'a dyn Fn(&str) -> Option<String>,
+}
+
+impl<'a> Layers<'a> {
+    /// The env var when set and non-empty, else the file value.
+    fn get<T>(
+        &self,
+        env_key: &'static str,
+        file_key: &'static str,
+        file_value: Option<T>,
+    ) -> Result<Option<Setting<'a, T>>, AppError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if let Some(raw) = (self.env)(env_key).filter(|v| !v.trim().is_empty()) {
+            let source = Source::Env(env_key);
+            let value = raw
+                .trim()
+                .parse()
+                .map_err(|e| AppError::Config(format!("invalid {source}: {e}")))?;
+            return Ok(Some(Setting { value, source }));
+        }
+        Ok(file_value.map(|value| Setting {
+            value,
+            source: Source::File(file_key, self.path.unwrap_or(Path::new("config file"))),
+        }))
+    }
+
+    /// A comma-separated env var when set and non-empty, else the file's
+    /// array. An empty env var must not clear a list such as the allowlist.
+    fn list(
+        &self,
+        env_key: &'static str,
+        file_key: &'static str,
+        file_value: Option<Vec<String>>,
+    ) -> Option<Setting<'a, Vec<String>>> {
+        if let Some(raw) = (self.env)(env_key).filter(|v| !v.trim().is_empty()) {
+            let value = raw
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_owned)
+                .collect();
+            return Some(Setting {
+                value,
+                source: Source::Env(env_key),
+            });
+        }
+        file_value.map(|value| Setting {
+            value,
+            source: Source::File(file_key, self.path.unwrap_or(Path::new("config file"))),
+        })
+    }
+
+    fn url(
+        &self,
+        env_key: &'static str,
+        file_key: &'static str,
+        file_value: Option<String>,
+    ) -> Result<Option<Url>, AppError> {
+        self.get(env_key, file_key, file_value)?
+            .map(|s| Url::parse(&s.value).map_err(|e| s.invalid(e)))
+            .transpose()
+    }
+
+    fn positive<T>(
+        &self,
+        env_key: &'static str,
+        file_key: &'static str,
+        file_value: Option<T>,
+    ) -> Result<Option<T>, AppError>
+    where
+        T: FromStr + Default + PartialEq,
+        T::Err: fmt::Display,
+    {
+        match self.get(env_key, file_key, file_value)? {
+            Some(s) if s.value == T::default() => Err(s.invalid("must be greater than 0")),
+            other => Ok(other.map(|s| s.value)),
+        }
+    }
+}
+
+fn required<T>(
+    setting: Option<Setting<'_, T>>,
+    env_key: &str,
+    file_key: &str,
+) -> Result<T, AppError> {
+    setting
+        .map(|s| s.value)
+        .ok_or_else(|| missing(env_key, file_key))
+}
+
+fn missing(env_key: &str, file_key: &str) -> AppError {
+    AppError::Config(format!(
+        "missing required setting: {env_key} (or {file_key} in the config file)"
+    ))
+}
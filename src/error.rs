@@ -5,6 +5,9 @@ use axum::{
 };
 use serde::Serialize;
 use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::utils;
 
 #[derive(Debug, Error)]
 pub enum AppError {
@@ -14,17 +17,28 @@ pub enum AppError {
     Auth,
     #[error("forbidden")]
     Forbidden,
+    #[error("insufficient token scopes: {0}")]
+    InsufficientScopes(String),
     #[error("bad request: {0}")]
     BadRequest(String),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error("unprocessable request: {0}")]
+    Unprocessable(String),
     #[error("upstream error: {0}")]
     Upstream(String),
     #[error("internal error")]
     Internal,
 }
 
-#[derive(Serialize)]
-struct ErrorBody {
-    error: String,
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+    /// Trace to look up in the tracing backend, when OTLP export is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
 }
 
 impl IntoResponse for AppError {
@@ -36,7 +50,14 @@ impl IntoResponse for AppError {
                 "authentication required".to_string(),
             ),
             Self::Forbidden => (StatusCode::FORBIDDEN, "forbidden".to_string()),
+            Self::InsufficientScopes(msg) => (
+                StatusCode::FORBIDDEN,
+                format!("insufficient token scopes: {msg}"),
+            ),
             Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            Self::Unprocessable(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
             Self::Upstream(msg) => (StatusCode::BAD_GATEWAY, msg),
             Self::Internal => (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -44,13 +65,19 @@ impl IntoResponse for AppError {
             ),
         };
 
-        (status, Json(ErrorBody { error: message })).into_response()
+        let body = ErrorBody {
+            error: message,
+            trace_id: utils::current_trace_id(),
+        };
+        (status, Json(body)).into_response()
     }
 }
 
 impl From<reqwest::Error> for AppError {
     fn from(err: reqwest::Error) -> Self {
-        Self::Upstream(err.to_string())
+        // URLs can carry OAuth codes or tokens in their query string, and this
+        // message is both logged and returned to the client.
+        Self::Upstream(err.without_url().to_string())
     }
 }
 
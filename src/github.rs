@@ -1,28 +1,69 @@
 use std::{
     sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use async_trait::async_trait;
 use futures::{StreamExt, stream};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
 use reqwest::{
     Client, RequestBuilder, Response, StatusCode,
     header::{HeaderMap, RETRY_AFTER},
 };
+use serde::de::DeserializeOwned;
 use tokio::{sync::Semaphore, time::sleep};
-use tracing::{info, warn};
+use tracing::{Instrument, field, info, info_span, warn};
+use url::Url;
 
 use crate::{
     error::AppError,
+    github_app::GitHubAppAuth,
+    metrics,
     models::{
-        Collaborator, CollaboratorPermission, GitHubUser, RepoFilterOptions, RepoWithCollaborators,
-        Repository,
+        Collaborator, CollaboratorPermission, DeployKey, GitHubUser, Installation,
+        InstallationAccessToken, InstallationRepositories, Owner, RateLimitBucket,
+        RateLimitResponse, RepoAudit, RepoDeployKeys, RepoFilterOptions, RepoWithCollaborators,
+        Repository, SsoStatus, TokenIdentity,
     },
+    teams::{Team, TeamRepository, TeamRole},
     utils,
 };
 
+/// Characters escaped in a path segment: all but RFC 3986's unreserved set.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
 #[derive(Clone)]
 pub struct GitHubClient {
     pub http: Client,
+    api_url: String,
+    retry: RetryPolicy,
+}
+
+/// How `GitHubClient` retries rate-limited requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per request, including the first one.
+    pub max_attempts: u8,
+    /// Wait when a rate-limited response carries no `retry-after` or reset time.
+    pub default_backoff: Duration,
+    /// Upper bound on any single wait, whatever GitHub asks for.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            // GitHub recommends waiting at least one minute when secondary rate
+            // limiting occurs without explicit timing headers.
+            default_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(15 * 60),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -31,52 +72,732 @@ pub enum CollaboratorFetchOutcome {
     Forbidden,
 }
 
-impl GitHubClient {
-    pub fn new() -> Result<Self, AppError> {
-        let http = Client::builder()
-            .user_agent("collaborator-audit-dashboard")
-            .build()
-            .map_err(|e| AppError::Config(format!("failed to build HTTP client: {e}")))?;
+enum RepoAuditOutcome {
+    Audited(RepoWithCollaborators),
+    Forbidden(Repository),
+    Empty,
+}
+
+/// The GitHub operations the audit and removal flows depend on. `GitHubClient`
+/// talks to the real API; `github_fake::FakeGitHub` serves fixtures so the
+/// handlers and `AuditService` can run without the network.
+#[async_trait]
+pub trait GitHubApi: Send + Sync {
+    async fn fetch_token_identity(&self, token: &str) -> Result<TokenIdentity, AppError>;
+
+    async fn fetch_owned_repos(
+        &self,
+        token: &str,
+        options: &RepoFilterOptions,
+    ) -> Result<Vec<Repository>, AppError>;
+
+    async fn fetch_repo_collaborators(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<CollaboratorFetchOutcome, AppError>;
+
+    /// Repositories of `org` that the token can see.
+    async fn fetch_org_repos(
+        &self,
+        token: &str,
+        org: &str,
+        options: &RepoFilterOptions,
+    ) -> Result<Vec<Repository>, AppError>;
+
+    async fn fetch_user_orgs(&self, token: &str) -> Result<Vec<Owner>, AppError>;
+
+    async fn fetch_org_sso_status(&self, token: &str, org: &str) -> Result<SsoStatus, AppError>;
+
+    async fn fetch_rate_limit(&self, token: &str) -> Result<RateLimitBucket, AppError>;
+
+    async fn fetch_effective_permission(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<Option<CollaboratorPermission>, AppError>;
+
+    async fn repo_exists_for_owner(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<bool, AppError>;
+
+    /// Removes `username` from `owner/repo`, cancelling any pending
+    /// invitation too. Returns GitHub's status: 204 when removed.
+    async fn remove_collaborator(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError>;
+
+    /// Deploy keys of `owner/repo`, or `None` when GitHub refuses to list
+    /// them (`403` or `404`), as it does for anyone without admin.
+    async fn fetch_deploy_keys(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<Vec<DeployKey>>, AppError>;
+
+    /// Deletes deploy key `key_id` from `owner/repo`. Returns GitHub's
+    /// status: 204 when deleted, 404 when no such key exists.
+    async fn remove_deploy_key(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+        key_id: u64,
+    ) -> Result<StatusCode, AppError>;
+
+    /// Users with access to `org` repositories without being members.
+    async fn fetch_outside_collaborators(
+        &self,
+        token: &str,
+        org: &str,
+    ) -> Result<Vec<GitHubUser>, AppError>;
+
+    async fn fetch_org_teams(&self, token: &str, org: &str) -> Result<Vec<Team>, AppError>;
+
+    async fn fetch_team_members(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        role: TeamRole,
+    ) -> Result<Vec<GitHubUser>, AppError>;
+
+    async fn fetch_team_repos(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+    ) -> Result<Vec<TeamRepository>, AppError>;
+
+    /// Revokes the team's access to `org/repo`. Returns GitHub's status.
+    async fn remove_team_repo(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        repo: &str,
+    ) -> Result<StatusCode, AppError>;
+
+    /// Removes `username` from the team. Returns GitHub's status.
+    async fn remove_team_member(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError>;
+
+    /// Removes an outside collaborator from every repository of `org` at once.
+    /// Returns GitHub's status, as `remove_collaborator` does.
+    async fn remove_outside_collaborator(
+        &self,
+        token: &str,
+        org: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError>;
+
+    async fn fetch_repos_with_collaborators(
+        &self,
+        token: &str,
+        viewer: &str,
+        options: RepoFilterOptions,
+        max_concurrency: usize,
+    ) -> Result<Vec<RepoWithCollaborators>, AppError> {
+        Ok(self
+            .fetch_repo_audit(token, viewer, options, max_concurrency)
+            .await?
+            .rows)
+    }
+
+    /// Like `fetch_repos_with_collaborators`, but also reports the repositories
+    /// whose collaborator list came back `Forbidden`.
+    async fn fetch_repo_audit(
+        &self,
+        token: &str,
+        viewer: &str,
+        options: RepoFilterOptions,
+        max_concurrency: usize,
+    ) -> Result<RepoAudit, AppError> {
+        let repos = self.fetch_owned_repos(token, &options).await?;
+        let targets = repos
+            .into_iter()
+            .map(|repo| (repo, token.to_string()))
+            .collect();
+
+        audit_repositories(self, targets, viewer, max_concurrency).await
+    }
+
+    /// Like `fetch_repos_with_collaborators`, for the repositories of `org`.
+    async fn fetch_org_repos_with_collaborators(
+        &self,
+        token: &str,
+        viewer: &str,
+        org: &str,
+        options: RepoFilterOptions,
+        max_concurrency: usize,
+    ) -> Result<Vec<RepoWithCollaborators>, AppError> {
+        let repos = self.fetch_org_repos(token, org, &options).await?;
+        let targets = repos
+            .into_iter()
+            .map(|repo| (repo, token.to_string()))
+            .collect();
+
+        Ok(audit_repositories(self, targets, viewer, max_concurrency)
+            .await?
+            .rows)
+    }
+
+    /// Deploy keys of every repository owned by the token's user, sorted by
+    /// repository name.
+    async fn fetch_repos_with_deploy_keys(
+        &self,
+        token: &str,
+        options: RepoFilterOptions,
+        max_concurrency: usize,
+    ) -> Result<Vec<RepoDeployKeys>, AppError> {
+        let repos = self.fetch_owned_repos(token, &options).await?;
+        let semaphore = Arc::new(Semaphore::new(max_concurrency.max(1)));
+
+        let mut rows = stream::iter(repos.into_iter().map(|repo| {
+            let semaphore = semaphore.clone();
+            let span = info_span!("deploy_keys", owner = %repo.owner.login, repo = %repo.name);
+
+            async move {
+                let _permit = semaphore
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| AppError::Internal)?;
+                let keys = self
+                    .fetch_deploy_keys(token, &repo.owner.login, &repo.name)
+                    .await?;
+                Ok::<_, AppError>(RepoDeployKeys { repo, keys })
+            }
+            .instrument(span)
+        }))
+        .buffer_unordered(max_concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        rows.sort_by(|a, b| a.repo.name.cmp(&b.repo.name));
+        Ok(rows)
+    }
+}
+
+impl GitHubClient {
+    pub fn new(api_url: &Url) -> Result<Self, AppError> {
+        let http = Client::builder()
+            .user_agent("collaborator-audit-dashboard")
+            .build()
+            .map_err(|e| AppError::Config(format!("failed to build HTTP client: {e}")))?;
+
+        Ok(Self {
+            http,
+            api_url: api_url.as_str().trim_end_matches('/').to_string(),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub async fn fetch_authenticated_user(&self, token: &str) -> Result<GitHubUser, AppError> {
+        let request = self
+            .http
+            .get(self.endpoint(&["user"]))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .bearer_auth(token);
+        let response = self.send("/user", request, 0).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::Auth);
+        }
+
+        let user = response.json::<GitHubUser>().await?;
+        Ok(user)
+    }
+
+    /// Audits the repositories of the App's installations on `accounts`;
+    /// installations on any other account are skipped.
+    pub async fn fetch_installation_repos_with_collaborators(
+        &self,
+        app: &GitHubAppAuth,
+        viewer: &str,
+        accounts: &[&str],
+        options: RepoFilterOptions,
+        max_concurrency: usize,
+    ) -> Result<Vec<RepoWithCollaborators>, AppError> {
+        let jwt = app.app_jwt()?;
+        let installations = self.fetch_app_installations(&jwt).await?;
+        let mut targets = Vec::new();
+
+        for installation in installations.into_iter().filter(|installation| {
+            accounts
+                .iter()
+                .any(|account| account.eq_ignore_ascii_case(&installation.account.login))
+        }) {
+            let token = app.installation_token(self, installation.id).await?;
+            let repos = self.fetch_installation_repos(&token, &options).await?;
+            info!(
+                installation_id = installation.id,
+                account = installation.account.login,
+                repo_count = repos.len(),
+                "fetched installation repositories"
+            );
+            targets.extend(repos.into_iter().map(|repo| (repo, token.clone())));
+        }
+
+        Ok(audit_repositories(self, targets, viewer, max_concurrency)
+            .await?
+            .rows)
+    }
+
+    pub async fn fetch_app_installations(&self, jwt: &str) -> Result<Vec<Installation>, AppError> {
+        let mut next_url = Some(format!(
+            "{}?per_page=100",
+            self.endpoint(&["app", "installations"])
+        ));
+        let mut installations = Vec::new();
+
+        while let Some(url) = next_url {
+            let response = self
+                .send_with_retry("/app/installations", || {
+                    self.authorized_request(self.http.get(url.clone()), jwt)
+                })
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(AppError::Upstream(format!(
+                    "failed to fetch app installations: {}",
+                    response.status()
+                )));
+            }
+
+            next_url = utils::parse_next_link(
+                response.headers().get("link").and_then(|v| v.to_str().ok()),
+            );
+            installations.extend(response.json::<Vec<Installation>>().await?);
+        }
+
+        Ok(installations)
+    }
+
+    pub async fn create_installation_token(
+        &self,
+        jwt: &str,
+        installation_id: u64,
+    ) -> Result<InstallationAccessToken, AppError> {
+        let endpoint = self.endpoint(&[
+            "app",
+            "installations",
+            &installation_id.to_string(),
+            "access_tokens",
+        ]);
+        let response = self
+            .send_with_retry("/app/installations/{id}/access_tokens", || {
+                self.authorized_request(self.http.post(endpoint.clone()), jwt)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::Upstream(format!(
+                "failed to create installation token for {installation_id}: {}",
+                response.status()
+            )));
+        }
+
+        Ok(response.json::<InstallationAccessToken>().await?)
+    }
+
+    pub async fn fetch_installation_repos(
+        &self,
+        token: &str,
+        options: &RepoFilterOptions,
+    ) -> Result<Vec<Repository>, AppError> {
+        let mut next_url = Some(format!(
+            "{}?per_page=100",
+            self.endpoint(&["installation", "repositories"])
+        ));
+        let mut repositories = Vec::new();
+
+        while let Some(url) = next_url {
+            let response = self
+                .send_with_retry("/installation/repositories", || {
+                    self.authorized_request(self.http.get(url.clone()), token)
+                })
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(AppError::Upstream(format!(
+                    "failed to fetch installation repositories: {}",
+                    response.status()
+                )));
+            }
+
+            next_url = utils::parse_next_link(
+                response.headers().get("link").and_then(|v| v.to_str().ok()),
+            );
+
+            let page = response.json::<InstallationRepositories>().await?;
+            repositories.extend(page.repositories.into_iter().filter(|repo| {
+                !(options.ignore_forks && repo.fork || options.ignore_archived && repo.archived)
+            }));
+        }
+
+        Ok(repositories)
+    }
+
+    /// Revokes a single OAuth access token on behalf of the OAuth app. GitHub
+    /// answers 404 when the token is already invalid.
+    pub async fn revoke_oauth_token(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        token: &str,
+    ) -> Result<StatusCode, AppError> {
+        let endpoint = self.endpoint(&["applications", client_id, "token"]);
+        let request = self
+            .http
+            .delete(endpoint)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .basic_auth(client_id, Some(client_secret))
+            .json(&serde_json::json!({ "access_token": token }));
+        let response = self
+            .send("/applications/{client_id}/token", request, 0)
+            .await?;
+
+        Ok(response.status())
+    }
+
+    /// Follows `Link: rel="next"` from `first_url`, keeping the repositories
+    /// `options` does not filter out.
+    async fn fetch_repo_pages(
+        &self,
+        token: &str,
+        route: &'static str,
+        first_url: String,
+        options: &RepoFilterOptions,
+    ) -> Result<Vec<Repository>, AppError> {
+        let mut next_url = Some(first_url);
+        let mut repositories = Vec::new();
+
+        while let Some(url) = next_url {
+            let response = self
+                .send_with_retry(route, || {
+                    self.authorized_request(self.http.get(url.clone()), token)
+                })
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(AppError::Upstream(format!(
+                    "failed to fetch repositories: {}",
+                    response.status()
+                )));
+            }
+
+            let next_link = utils::parse_next_link(
+                response.headers().get("link").and_then(|v| v.to_str().ok()),
+            );
+
+            let page_repos = response.json::<Vec<Repository>>().await?;
+            if page_repos.is_empty() {
+                break;
+            }
+
+            repositories.extend(page_repos.into_iter().filter(|repo| {
+                !(options.ignore_forks && repo.fork || options.ignore_archived && repo.archived)
+            }));
+
+            next_url = next_link;
+        }
+
+        Ok(repositories)
+    }
+
+    /// Follows `Link: rel="next"` from `first_url` and collects every page.
+    /// `what` names the list in the error when a page fails.
+    async fn fetch_all_pages<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        route: &'static str,
+        first_url: String,
+        what: &str,
+    ) -> Result<Vec<T>, AppError> {
+        let mut next_url = Some(first_url);
+        let mut items = Vec::new();
+
+        while let Some(url) = next_url {
+            let response = self
+                .send_with_retry(route, || {
+                    self.authorized_request(self.http.get(url.clone()), token)
+                })
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(AppError::Upstream(format!(
+                    "failed to fetch {what}: {}",
+                    response.status()
+                )));
+            }
+
+            let next_link = utils::parse_next_link(
+                response.headers().get("link").and_then(|v| v.to_str().ok()),
+            );
+
+            let page = response.json::<Vec<T>>().await?;
+            if page.is_empty() {
+                break;
+            }
+
+            items.extend(page);
+            next_url = next_link;
+        }
+
+        Ok(items)
+    }
+
+    /// `api_url` followed by `segments`, each percent-encoded so a name
+    /// containing `/` stays a single path segment.
+    fn endpoint(&self, segments: &[&str]) -> String {
+        let mut url = self.api_url.clone();
+        for segment in segments {
+            url.push('/');
+            url.extend(utf8_percent_encode(segment, PATH_SEGMENT));
+        }
+        url
+    }
+
+    pub fn is_admin_permission(permission: &CollaboratorPermission) -> bool {
+        permission.permission.eq_ignore_ascii_case("admin")
+            || permission
+                .role_name
+                .as_ref()
+                .is_some_and(|value| value.eq_ignore_ascii_case("admin"))
+    }
+
+    fn authorized_request(&self, request: RequestBuilder, token: &str) -> RequestBuilder {
+        request
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .bearer_auth(token)
+    }
+
+    /// Sends a request, recording its count and latency under `endpoint`, the
+    /// path template rather than the concrete URL so label values stay bounded.
+    ///
+    /// Each attempt gets its own span; `retry` is 0 for the first one.
+    async fn send(
+        &self,
+        endpoint: &'static str,
+        request: RequestBuilder,
+        retry: u8,
+    ) -> Result<Response, AppError> {
+        let request = request.build()?;
+        let method = request.method().to_string();
+        let span = info_span!(
+            "github_request",
+            http.request.method = method.as_str(),
+            url.template = endpoint,
+            http.response.status_code = field::Empty,
+            retry,
+        );
+        let started = Instant::now();
+        let result = self.http.execute(request).instrument(span.clone()).await;
+        if let Ok(response) = &result {
+            span.record("http.response.status_code", response.status().as_u16());
+        }
+
+        let m = metrics::metrics();
+        let status = match &result {
+            Ok(response) => response.status().as_u16().to_string(),
+            Err(_) => "error".to_string(),
+        };
+        m.github_request_duration
+            .with_label_values(&[endpoint, method.as_str(), status.as_str()])
+            .observe(started.elapsed().as_secs_f64());
+        m.github_requests
+            .with_label_values(&[endpoint, method.as_str(), status.as_str()])
+            .inc();
+
+        Ok(result?)
+    }
+
+    async fn send_with_retry<F>(
+        &self,
+        endpoint: &'static str,
+        mut build: F,
+    ) -> Result<Response, AppError>
+    where
+        F: FnMut() -> RequestBuilder,
+    {
+        for attempt in 1..=self.retry.max_attempts {
+            let response = self.send(endpoint, build(), attempt - 1).await?;
+
+            if let Some(backoff) = self.rate_limit_backoff(response.status(), response.headers()) {
+                let backoff_ms = backoff.as_millis() as u64;
+                warn!(attempt, backoff_ms, endpoint, "rate limit hit, backing off");
+                metrics::metrics()
+                    .github_backoffs
+                    .with_label_values(&[endpoint])
+                    .inc();
+                sleep(backoff).await;
+                continue;
+            }
+
+            return Ok(response);
+        }
+
+        Err(AppError::Upstream(
+            "request failed repeatedly due to rate limiting".to_string(),
+        ))
+    }
+
+    fn rate_limit_backoff(&self, status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+        if status != StatusCode::TOO_MANY_REQUESTS
+            && !(status == StatusCode::FORBIDDEN && Self::is_rate_limited(headers))
+        {
+            return None;
+        }
+
+        let delay = Self::retry_after_delay(headers)
+            .or_else(|| Self::reset_time_delay(headers))
+            .unwrap_or(self.retry.default_backoff);
+        Some(delay.min(self.retry.max_backoff))
+    }
+
+    fn is_rate_limited(headers: &HeaderMap) -> bool {
+        headers
+            .get("x-ratelimit-remaining")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v == "0")
+            || headers.contains_key(RETRY_AFTER)
+    }
+
+    fn retry_after_delay(headers: &HeaderMap) -> Option<Duration> {
+        let retry_after = headers
+            .get(RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .parse::<u64>()
+            .ok()?;
+        Some(Duration::from_secs(retry_after.max(1)))
+    }
+
+    fn reset_time_delay(headers: &HeaderMap) -> Option<Duration> {
+        let reset_at = headers
+            .get("x-ratelimit-reset")?
+            .to_str()
+            .ok()?
+            .parse::<u64>()
+            .ok()?;
 
-        Ok(Self { http })
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let wait = if reset_at > now { reset_at - now } else { 1 };
+        Some(Duration::from_secs(wait))
     }
+}
 
-    pub async fn fetch_authenticated_user(&self, token: &str) -> Result<GitHubUser, AppError> {
-        let response = self
-            .http
-            .get("https://api.github.com/user")
-            .header("Accept", "application/vnd.github+json")
-            .header("X-GitHub-Api-Version", "2022-11-28")
-            .bearer_auth(token)
-            .send()
-            .await?;
+#[async_trait]
+impl GitHubApi for GitHubClient {
+    async fn fetch_token_identity(&self, token: &str) -> Result<TokenIdentity, AppError> {
+        let request = self.authorized_request(self.http.get(self.endpoint(&["user"])), token);
+        let response = self.send("/user", request, 0).await?;
 
         if !response.status().is_success() {
             return Err(AppError::Auth);
         }
 
+        let scopes = response
+            .headers()
+            .get("x-oauth-scopes")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
         let user = response.json::<GitHubUser>().await?;
-        Ok(user)
+
+        Ok(TokenIdentity { user, scopes })
     }
 
-    pub async fn fetch_owned_repos(
+    async fn fetch_owned_repos(
         &self,
         token: &str,
         options: &RepoFilterOptions,
     ) -> Result<Vec<Repository>, AppError> {
-        let mut next_url = Some(
-            "https://api.github.com/user/repos?affiliation=owner&per_page=100&page=1".to_string(),
-        );
-        let mut repositories = Vec::new();
+        self.fetch_repo_pages(
+            token,
+            "/user/repos",
+            format!(
+                "{}?affiliation=owner&per_page=100&page=1",
+                self.endpoint(&["user", "repos"])
+            ),
+            options,
+        )
+        .await
+    }
+
+    async fn fetch_org_repos(
+        &self,
+        token: &str,
+        org: &str,
+        options: &RepoFilterOptions,
+    ) -> Result<Vec<Repository>, AppError> {
+        self.fetch_repo_pages(
+            token,
+            "/orgs/{org}/repos",
+            format!(
+                "{}?type=all&per_page=100&page=1",
+                self.endpoint(&["orgs", org, "repos"])
+            ),
+            options,
+        )
+        .await
+    }
+
+    async fn fetch_repo_collaborators(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<CollaboratorFetchOutcome, AppError> {
+        let mut next_url = Some(format!(
+            "{}?per_page=100&page=1",
+            self.endpoint(&["repos", owner, repo, "collaborators"])
+        ));
+        let mut collaborators = Vec::new();
 
         while let Some(url) = next_url {
             let response = self
-                .send_with_retry(|| self.authorized_request(self.http.get(url.clone()), token))
+                .send_with_retry("/repos/{owner}/{repo}/collaborators", || {
+                    self.authorized_request(self.http.get(url.clone()), token)
+                })
                 .await?;
 
+            if response.status() == StatusCode::FORBIDDEN {
+                warn!(
+                    owner,
+                    repo, "insufficient permissions while fetching collaborators"
+                );
+                return Ok(CollaboratorFetchOutcome::Forbidden);
+            }
+
             if !response.status().is_success() {
                 return Err(AppError::Upstream(format!(
-                    "failed to fetch repositories: {}",
+                    "failed to fetch collaborators for {owner}/{repo}: {}",
                     response.status()
                 )));
             }
@@ -85,48 +806,51 @@ impl GitHubClient {
                 response.headers().get("link").and_then(|v| v.to_str().ok()),
             );
 
-            let page_repos = response.json::<Vec<Repository>>().await?;
-            if page_repos.is_empty() {
+            let page_collaborators = response.json::<Vec<Collaborator>>().await?;
+            if page_collaborators.is_empty() {
                 break;
             }
 
-            repositories.extend(page_repos.into_iter().filter(|repo| {
-                !(options.ignore_forks && repo.fork || options.ignore_archived && repo.archived)
-            }));
-
+            collaborators.extend(page_collaborators);
             next_url = next_link;
         }
 
-        Ok(repositories)
+        Ok(CollaboratorFetchOutcome::Success(collaborators))
     }
 
-    pub async fn fetch_repo_collaborators(
+    async fn fetch_deploy_keys(
         &self,
         token: &str,
         owner: &str,
         repo: &str,
-    ) -> Result<CollaboratorFetchOutcome, AppError> {
+    ) -> Result<Option<Vec<DeployKey>>, AppError> {
         let mut next_url = Some(format!(
-            "https://api.github.com/repos/{owner}/{repo}/collaborators?per_page=100&page=1"
+            "{}?per_page=100&page=1",
+            self.endpoint(&["repos", owner, repo, "keys"])
         ));
-        let mut collaborators = Vec::new();
+        let mut keys = Vec::new();
 
         while let Some(url) = next_url {
             let response = self
-                .send_with_retry(|| self.authorized_request(self.http.get(url.clone()), token))
+                .send_with_retry("/repos/{owner}/{repo}/keys", || {
+                    self.authorized_request(self.http.get(url.clone()), token)
+                })
                 .await?;
 
-            if response.status() == StatusCode::FORBIDDEN {
+            if matches!(
+                response.status(),
+                StatusCode::FORBIDDEN | StatusCode::NOT_FOUND
+            ) {
                 warn!(
                     owner,
-                    repo, "insufficient permissions while fetching collaborators"
+                    repo, "insufficient permissions while fetching deploy keys"
                 );
-                return Ok(CollaboratorFetchOutcome::Forbidden);
+                return Ok(None);
             }
 
             if !response.status().is_success() {
                 return Err(AppError::Upstream(format!(
-                    "failed to fetch collaborators for {owner}/{repo}: {}",
+                    "failed to fetch deploy keys for {owner}/{repo}: {}",
                     response.status()
                 )));
             }
@@ -135,122 +859,128 @@ impl GitHubClient {
                 response.headers().get("link").and_then(|v| v.to_str().ok()),
             );
 
-            let page_collaborators = response.json::<Vec<Collaborator>>().await?;
-            if page_collaborators.is_empty() {
+            let page_keys = response.json::<Vec<DeployKey>>().await?;
+            if page_keys.is_empty() {
                 break;
             }
 
-            collaborators.extend(page_collaborators);
+            keys.extend(page_keys);
             next_url = next_link;
         }
 
-        Ok(CollaboratorFetchOutcome::Success(collaborators))
+        Ok(Some(keys))
     }
 
-    pub async fn fetch_repos_with_collaborators(
+    async fn remove_deploy_key(
         &self,
         token: &str,
-        viewer: &str,
-        options: RepoFilterOptions,
-        max_concurrency: usize,
-    ) -> Result<Vec<RepoWithCollaborators>, AppError> {
-        let repos = self.fetch_owned_repos(token, &options).await?;
-        let semaphore = Arc::new(Semaphore::new(max_concurrency));
-        let client = self.clone();
-        let viewer_login = viewer.to_string();
+        owner: &str,
+        repo: &str,
+        key_id: u64,
+    ) -> Result<StatusCode, AppError> {
+        let endpoint = self.endpoint(&["repos", owner, repo, "keys", &key_id.to_string()]);
 
-        let rows = stream::iter(repos.into_iter().map(|repo| {
-            let semaphore = semaphore.clone();
-            let client = client.clone();
-            let token = token.to_string();
-            let viewer_login = viewer_login.clone();
+        let response = self
+            .send_with_retry("/repos/{owner}/{repo}/keys/{key_id}", || {
+                self.authorized_request(self.http.delete(endpoint.clone()), token)
+            })
+            .await?;
 
-            async move {
-                let permit = semaphore
-                    .acquire_owned()
-                    .await
-                    .map_err(|_| AppError::Internal)?;
-                let owner = repo.owner.login.clone();
-                let repo_name = repo.name.clone();
-
-                let collaborators = match client
-                    .fetch_repo_collaborators(&token, &owner, &repo_name)
-                    .await?
-                {
-                    CollaboratorFetchOutcome::Success(c) => c,
-                    CollaboratorFetchOutcome::Forbidden => {
-                        drop(permit);
-                        return Ok(None);
-                    }
-                };
-
-                let filtered = collaborators
-                    .into_iter()
-                    .filter(|c| c.login != viewer_login)
-                    .collect::<Vec<_>>();
-
-                if filtered.is_empty() {
-                    drop(permit);
-                    return Ok(None);
-                }
+        Ok(response.status())
+    }
 
-                let can_remove = match client
-                    .fetch_effective_permission(&token, &owner, &repo_name, &viewer_login)
-                    .await
-                {
-                    Ok(Some(permission)) => Self::is_admin_permission(&permission),
-                    Ok(None) => false,
-                    Err(err) => {
-                        warn!(
-                            owner,
-                            repo = repo_name,
-                            error = %err,
-                            "permission check failed, disabling removal"
-                        );
-                        false
-                    }
-                };
+    async fn fetch_user_orgs(&self, token: &str) -> Result<Vec<Owner>, AppError> {
+        let mut next_url = Some(format!("{}?per_page=100", self.endpoint(&["user", "orgs"])));
+        let mut orgs = Vec::new();
 
-                drop(permit);
+        while let Some(url) = next_url {
+            let response = self
+                .send_with_retry("/user/orgs", || {
+                    self.authorized_request(self.http.get(url.clone()), token)
+                })
+                .await?;
 
-                Ok(Some(RepoWithCollaborators {
-                    repo,
-                    collaborators: filtered,
-                    can_remove,
-                }))
+            if !response.status().is_success() {
+                return Err(AppError::Upstream(format!(
+                    "failed to fetch organizations: {}",
+                    response.status()
+                )));
             }
-        }))
-        .buffer_unordered(max_concurrency)
-        .collect::<Vec<Result<Option<RepoWithCollaborators>, AppError>>>()
-        .await;
 
-        let mut output = Vec::new();
-        for item in rows {
-            if let Some(repo) = item? {
-                output.push(repo);
-            }
+            next_url = utils::parse_next_link(
+                response.headers().get("link").and_then(|v| v.to_str().ok()),
+            );
+            orgs.extend(response.json::<Vec<Owner>>().await?);
         }
 
-        info!(
-            repo_count = output.len(),
-            "fetched repositories with collaborators"
-        );
-        Ok(output)
+        Ok(orgs)
+    }
+
+    /// Probes an organization's repository list. Organizations enforcing SAML
+    /// SSO answer 403 with an `x-github-sso` header until the token is authorized.
+    async fn fetch_org_sso_status(&self, token: &str, org: &str) -> Result<SsoStatus, AppError> {
+        let endpoint = format!("{}?per_page=1", self.endpoint(&["orgs", org, "repos"]));
+        let response = self
+            .send_with_retry("/orgs/{org}/repos", || {
+                self.authorized_request(self.http.get(endpoint.clone()), token)
+            })
+            .await?;
+
+        if response.status().is_success() {
+            return Ok(SsoStatus::Authorized);
+        }
+
+        if let Some(sso) = response
+            .headers()
+            .get("x-github-sso")
+            .and_then(|v| v.to_str().ok())
+        {
+            let authorize_url = sso
+                .split(';')
+                .map(str::trim)
+                .find_map(|part| part.strip_prefix("url="))
+                .map(str::to_owned);
+            return Ok(SsoStatus::Required { authorize_url });
+        }
+
+        Ok(SsoStatus::Unavailable(response.status().to_string()))
+    }
+
+    async fn fetch_rate_limit(&self, token: &str) -> Result<RateLimitBucket, AppError> {
+        let request = self.authorized_request(self.http.get(self.endpoint(&["rate_limit"])), token);
+        let response = self.send("/rate_limit", request, 0).await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::Upstream(format!(
+                "failed to fetch rate limit: {}",
+                response.status()
+            )));
+        }
+
+        Ok(response.json::<RateLimitResponse>().await?.resources.core)
     }
 
-    pub async fn fetch_effective_permission(
+    async fn fetch_effective_permission(
         &self,
         token: &str,
         owner: &str,
         repo: &str,
         username: &str,
     ) -> Result<Option<CollaboratorPermission>, AppError> {
-        let endpoint = format!(
-            "https://api.github.com/repos/{owner}/{repo}/collaborators/{username}/permission"
-        );
+        let endpoint = self.endpoint(&[
+            "repos",
+            owner,
+            repo,
+            "collaborators",
+            username,
+            "permission",
+        ]);
 
         let response = self
-            .send_with_retry(|| self.authorized_request(self.http.get(endpoint.clone()), token))
+            .send_with_retry(
+                "/repos/{owner}/{repo}/collaborators/{username}/permission",
+                || self.authorized_request(self.http.get(endpoint.clone()), token),
+            )
             .await?;
 
         if response.status() == StatusCode::NOT_FOUND {
@@ -267,15 +997,17 @@ impl GitHubClient {
         Ok(Some(response.json::<CollaboratorPermission>().await?))
     }
 
-    pub async fn repo_exists_for_owner(
+    async fn repo_exists_for_owner(
         &self,
         token: &str,
         owner: &str,
         repo: &str,
     ) -> Result<bool, AppError> {
-        let endpoint = format!("https://api.github.com/repos/{owner}/{repo}");
+        let endpoint = self.endpoint(&["repos", owner, repo]);
         let response = self
-            .send_with_retry(|| self.authorized_request(self.http.get(endpoint.clone()), token))
+            .send_with_retry("/repos/{owner}/{repo}", || {
+                self.authorized_request(self.http.get(endpoint.clone()), token)
+            })
             .await?;
 
         if response.status() == StatusCode::NOT_FOUND {
@@ -292,116 +1024,270 @@ impl GitHubClient {
         Ok(true)
     }
 
-    pub async fn remove_collaborator(
+    async fn remove_collaborator(
         &self,
         token: &str,
         owner: &str,
         repo: &str,
         username: &str,
     ) -> Result<StatusCode, AppError> {
-        let endpoint =
-            format!("https://api.github.com/repos/{owner}/{repo}/collaborators/{username}");
+        let endpoint = self.endpoint(&["repos", owner, repo, "collaborators", username]);
 
         let response = self
-            .send_with_retry(|| self.authorized_request(self.http.delete(endpoint.clone()), token))
+            .send_with_retry("/repos/{owner}/{repo}/collaborators/{username}", || {
+                self.authorized_request(self.http.delete(endpoint.clone()), token)
+            })
             .await?;
 
         Ok(response.status())
     }
 
-    pub fn is_admin_permission(permission: &CollaboratorPermission) -> bool {
-        permission.permission.eq_ignore_ascii_case("admin")
-            || permission
-                .role_name
-                .as_ref()
-                .is_some_and(|value| value.eq_ignore_ascii_case("admin"))
-    }
-
-    fn authorized_request(&self, request: RequestBuilder, token: &str) -> RequestBuilder {
-        request
-            .header("Accept", "application/vnd.github+json")
-            .header("X-GitHub-Api-Version", "2022-11-28")
-            .bearer_auth(token)
-    }
-
-    async fn send_with_retry<F>(&self, mut build: F) -> Result<Response, AppError>
-    where
-        F: FnMut() -> RequestBuilder,
-    {
-        let max_attempts = 5u8;
+    async fn fetch_outside_collaborators(
+        &self,
+        token: &str,
+        org: &str,
+    ) -> Result<Vec<GitHubUser>, AppError> {
+        let mut next_url = Some(format!(
+            "{}?per_page=100&page=1",
+            self.endpoint(&["orgs", org, "outside_collaborators"])
+        ));
+        let mut users = Vec::new();
 
-        for attempt in 1..=max_attempts {
-            let response = build().send().await?;
+        while let Some(url) = next_url {
+            let response = self
+                .send_with_retry("/orgs/{org}/outside_collaborators", || {
+                    self.authorized_request(self.http.get(url.clone()), token)
+                })
+                .await?;
 
-            if let Some(backoff) = Self::rate_limit_backoff(response.status(), response.headers()) {
-                let backoff_ms = backoff.as_millis() as u64;
-                warn!(attempt, backoff_ms, "rate limit hit, backing off");
-                sleep(backoff).await;
-                continue;
+            if !response.status().is_success() {
+                return Err(AppError::Upstream(format!(
+                    "failed to fetch outside collaborators for {org}: {}",
+                    response.status()
+                )));
             }
 
-            if response.status() != StatusCode::TOO_MANY_REQUESTS
-                && response.status() != StatusCode::FORBIDDEN
-            {
-                return Ok(response);
+            let next_link = utils::parse_next_link(
+                response.headers().get("link").and_then(|v| v.to_str().ok()),
+            );
+
+            let page_users = response.json::<Vec<GitHubUser>>().await?;
+            if page_users.is_empty() {
+                break;
             }
 
-            return Ok(response);
+            users.extend(page_users);
+            next_url = next_link;
         }
 
-        Err(AppError::Upstream(
-            "request failed repeatedly due to rate limiting".to_string(),
-        ))
+        Ok(users)
     }
 
-    fn rate_limit_backoff(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
-        if status != StatusCode::TOO_MANY_REQUESTS
-            && !(status == StatusCode::FORBIDDEN && Self::is_rate_limited(headers))
-        {
-            return None;
-        }
+    async fn remove_outside_collaborator(
+        &self,
+        token: &str,
+        org: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError> {
+        let endpoint = self.endpoint(&["orgs", org, "outside_collaborators", username]);
 
-        if let Some(delay) = Self::retry_after_delay(headers) {
-            return Some(delay);
-        }
+        let response = self
+            .send_with_retry("/orgs/{org}/outside_collaborators/{username}", || {
+                self.authorized_request(self.http.delete(endpoint.clone()), token)
+            })
+            .await?;
 
-        if let Some(delay) = Self::reset_time_delay(headers) {
-            return Some(delay);
-        }
+        Ok(response.status())
+    }
 
-        // GitHub recommends waiting at least one minute when secondary rate limiting
-        // occurs without explicit timing headers.
-        Some(Duration::from_secs(60))
+    async fn fetch_org_teams(&self, token: &str, org: &str) -> Result<Vec<Team>, AppError> {
+        self.fetch_all_pages(
+            token,
+            "/orgs/{org}/teams",
+            format!(
+                "{}?per_page=100&page=1",
+                self.endpoint(&["orgs", org, "teams"])
+            ),
+            &format!("teams for {org}"),
+        )
+        .await
     }
 
-    fn is_rate_limited(headers: &HeaderMap) -> bool {
-        headers
-            .get("x-ratelimit-remaining")
-            .and_then(|v| v.to_str().ok())
-            .is_some_and(|v| v == "0")
-            || headers.contains_key(RETRY_AFTER)
+    async fn fetch_team_members(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        role: TeamRole,
+    ) -> Result<Vec<GitHubUser>, AppError> {
+        let role = role.as_str();
+        self.fetch_all_pages(
+            token,
+            "/orgs/{org}/teams/{team_slug}/members",
+            format!(
+                "{}?role={role}&per_page=100&page=1",
+                self.endpoint(&["orgs", org, "teams", team, "members"])
+            ),
+            &format!("{role}s of team {org}/{team}"),
+        )
+        .await
     }
 
-    fn retry_after_delay(headers: &HeaderMap) -> Option<Duration> {
-        let retry_after = headers
-            .get(RETRY_AFTER)?
-            .to_str()
-            .ok()?
-            .parse::<u64>()
-            .ok()?;
-        Some(Duration::from_secs(retry_after.max(1)))
+    async fn fetch_team_repos(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+    ) -> Result<Vec<TeamRepository>, AppError> {
+        self.fetch_all_pages(
+            token,
+            "/orgs/{org}/teams/{team_slug}/repos",
+            format!(
+                "{}?per_page=100&page=1",
+                self.endpoint(&["orgs", org, "teams", team, "repos"])
+            ),
+            &format!("repositories of team {org}/{team}"),
+        )
+        .await
     }
 
-    fn reset_time_delay(headers: &HeaderMap) -> Option<Duration> {
-        let reset_at = headers
-            .get("x-ratelimit-reset")?
-            .to_str()
-            .ok()?
-            .parse::<u64>()
-            .ok()?;
+    async fn remove_team_repo(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        repo: &str,
+    ) -> Result<StatusCode, AppError> {
+        let endpoint = self.endpoint(&["orgs", org, "teams", team, "repos", org, repo]);
 
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
-        let wait = if reset_at > now { reset_at - now } else { 1 };
-        Some(Duration::from_secs(wait))
+        let response = self
+            .send_with_retry("/orgs/{org}/teams/{team_slug}/repos/{owner}/{repo}", || {
+                self.authorized_request(self.http.delete(endpoint.clone()), token)
+            })
+            .await?;
+
+        Ok(response.status())
     }
+
+    async fn remove_team_member(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError> {
+        let endpoint = self.endpoint(&["orgs", org, "teams", team, "memberships", username]);
+
+        let response = self
+            .send_with_retry(
+                "/orgs/{org}/teams/{team_slug}/memberships/{username}",
+                || self.authorized_request(self.http.delete(endpoint.clone()), token),
+            )
+            .await?;
+
+        Ok(response.status())
+    }
+}
+
+/// Fetches collaborators for each repository using the token paired with it,
+/// so repositories from different installations can be audited together.
+async fn audit_repositories<A: GitHubApi + ?Sized>(
+    api: &A,
+    targets: Vec<(Repository, String)>,
+    viewer: &str,
+    max_concurrency: usize,
+) -> Result<RepoAudit, AppError> {
+    let semaphore = Arc::new(Semaphore::new(max_concurrency));
+    let viewer_login = viewer.to_string();
+
+    let rows = stream::iter(targets.into_iter().map(|(repo, token)| {
+        let semaphore = semaphore.clone();
+        let viewer_login = viewer_login.clone();
+        let span = info_span!("audit_repo", owner = %repo.owner.login, repo = %repo.name);
+
+        async move {
+            let permit = semaphore
+                .acquire_owned()
+                .await
+                .map_err(|_| AppError::Internal)?;
+            let owner = repo.owner.login.clone();
+            let repo_name = repo.name.clone();
+
+            let collaborators = match api
+                .fetch_repo_collaborators(&token, &owner, &repo_name)
+                .await?
+            {
+                CollaboratorFetchOutcome::Success(c) => c,
+                CollaboratorFetchOutcome::Forbidden => {
+                    drop(permit);
+                    return Ok(RepoAuditOutcome::Forbidden(repo));
+                }
+            };
+
+            let filtered = collaborators
+                .into_iter()
+                .filter(|c| c.login != viewer_login)
+                .collect::<Vec<_>>();
+
+            if filtered.is_empty() {
+                drop(permit);
+                return Ok(RepoAuditOutcome::Empty);
+            }
+
+            let can_remove = match api
+                .fetch_effective_permission(&token, &owner, &repo_name, &viewer_login)
+                .await
+            {
+                Ok(Some(permission)) => GitHubClient::is_admin_permission(&permission),
+                Ok(None) => false,
+                Err(err) => {
+                    warn!(
+                        owner,
+                        repo = repo_name,
+                        error = %err,
+                        "permission check failed, disabling removal"
+                    );
+                    false
+                }
+            };
+
+            drop(permit);
+
+            Ok(RepoAuditOutcome::Audited(RepoWithCollaborators {
+                repo,
+                collaborators: filtered,
+                can_remove,
+            }))
+        }
+        .instrument(span)
+    }))
+    .buffer_unordered(max_concurrency)
+    .collect::<Vec<Result<RepoAuditOutcome, AppError>>>()
+    .await;
+
+    let mut output = Vec::new();
+    let mut forbidden = Vec::new();
+    let repos_audited = &metrics::metrics().repos_audited;
+    for item in rows {
+        match item? {
+            RepoAuditOutcome::Audited(repo) => {
+                repos_audited.with_label_values(&["audited"]).inc();
+                output.push(repo);
+            }
+            RepoAuditOutcome::Forbidden(repo) => {
+                repos_audited.with_label_values(&["forbidden"]).inc();
+                forbidden.push(repo);
+            }
+            RepoAuditOutcome::Empty => repos_audited.with_label_values(&["empty"]).inc(),
+        }
+    }
+
+    info!(
+        repo_count = output.len(),
+        "fetched repositories with collaborators"
+    );
+    Ok(RepoAudit {
+        rows: output,
+        forbidden,
+    })
 }
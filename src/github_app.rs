@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use tracing::info;
+
+use crate::{config::GitHubAppConfig, error::AppError, github::GitHubClient};
+
+/// Installation tokens are refreshed this long before GitHub expires them so a
+/// token never runs out halfway through an audit.
+const TOKEN_EXPIRY_MARGIN: TimeDelta = TimeDelta::minutes(5);
+
+#[derive(Clone)]
+pub struct GitHubAppAuth {
+    app_id: u64,
+    key: EncodingKey,
+    tokens: Arc<Mutex<HashMap<u64, CachedToken>>>,
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct AppClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+impl GitHubAppAuth {
+    pub fn new(config: &GitHubAppConfig) -> Result<Self, AppError> {
+        let key = EncodingKey::from_rsa_pem(config.private_key_pem.as_bytes())
+            .map_err(|e| AppError::Config(format!("invalid GitHub App private key: {e}")))?;
+
+        Ok(Self {
+            app_id: config.app_id,
+            key,
+            tokens: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub fn app_jwt(&self) -> Result<String, AppError> {
+        let now = Utc::now().timestamp();
+        // GitHub rejects JWTs issued in the future and caps their lifetime at ten
+        // minutes, so backdate slightly to absorb clock drift.
+        let claims = AppClaims {
+            iat: now - 60,
+            exp: now + 9 * 60,
+            iss: self.app_id.to_string(),
+        };
+
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .map_err(|_| AppError::Internal)
+    }
+
+    /// Returns the cached token for the installation, or issues a new one when
+    /// it is missing or about to expire. The cache is not locked while GitHub
+    /// is asked, so installations never wait on each other's refresh.
+    pub async fn installation_token(
+        &self,
+        github: &GitHubClient,
+        installation_id: u64,
+    ) -> Result<String, AppError> {
+        if let Some(cached) = self
+            .tokens
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&installation_id)
+            && cached.expires_at - TOKEN_EXPIRY_MARGIN > Utc::now()
+        {
+            return Ok(cached.token.clone());
+        }
+
+        let jwt = self.app_jwt()?;
+        let issued = github
+            .create_installation_token(&jwt, installation_id)
+            .await?;
+        info!(
+            installation_id,
+            expires_at = %issued.expires_at,
+            "issued installation access token"
+        );
+
+        self.tokens
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                installation_id,
+                CachedToken {
+                    token: issued.token.clone(),
+                    expires_at: issued.expires_at,
+                },
+            );
+        Ok(issued.token)
+    }
+}
@@ -0,0 +1,502 @@
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    error::AppError,
+    github::{CollaboratorFetchOutcome, GitHubApi},
+    models::{
+        Collaborator, CollaboratorPermission, DeployKey, GitHubUser, Owner, Permissions,
+        RateLimitBucket, RepoFilterOptions, Repository, SsoStatus, TokenIdentity,
+    },
+    teams::{Team, TeamParent, TeamRepository, TeamRole},
+};
+
+/// Fixture describing the account a `FakeGitHub` pretends to be.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fixture {
+    /// Login of the authenticated user.
+    pub viewer: String,
+    /// Reported in place of `x-oauth-scopes`; `None` mimics a fine-grained token.
+    #[serde(default)]
+    pub scopes: Option<String>,
+    /// When set, only this token is accepted; otherwise any token is.
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub orgs: Vec<Owner>,
+    #[serde(default)]
+    pub repos: Vec<FixtureRepo>,
+    /// Outside collaborators by organization login.
+    #[serde(default)]
+    pub outside_collaborators: HashMap<String, Vec<String>>,
+    /// Teams by organization login.
+    #[serde(default)]
+    pub teams: HashMap<String, Vec<FixtureTeam>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureRepo {
+    #[serde(flatten)]
+    pub repo: Repository,
+    #[serde(default)]
+    pub collaborators: Vec<Collaborator>,
+    #[serde(default)]
+    pub deploy_keys: Vec<DeployKey>,
+    /// Answer collaborator and deploy key listing with 403, as GitHub does
+    /// without access.
+    #[serde(default)]
+    pub forbidden: bool,
+    /// Status returned for every collaborator DELETE on this repository,
+    /// overriding the default of 204, or 404 for an unknown collaborator.
+    #[serde(default)]
+    pub remove_status: Option<u16>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureTeam {
+    pub slug: String,
+    pub name: String,
+    /// Slug of the parent team, for nested teams.
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub maintainers: Vec<String>,
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Repositories of the organization the team can access, by name.
+    #[serde(default)]
+    pub repos: Vec<FixtureTeamRepo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureTeamRepo {
+    pub name: String,
+    #[serde(default)]
+    pub permissions: Permissions,
+    #[serde(default)]
+    pub role_name: Option<String>,
+}
+
+/// In-memory `GitHubApi` backed by a fixture. Removals mutate the fixture, so
+/// a later listing reflects them.
+pub struct FakeGitHub {
+    viewer: String,
+    scopes: Option<String>,
+    token: Option<String>,
+    orgs: Vec<Owner>,
+    repos: Mutex<Vec<FixtureRepo>>,
+    outside_collaborators: Mutex<HashMap<String, Vec<String>>>,
+    teams: Mutex<HashMap<String, Vec<FixtureTeam>>>,
+}
+
+impl FakeGitHub {
+    pub fn new(fixture: Fixture) -> Self {
+        Self {
+            viewer: fixture.viewer,
+            scopes: fixture.scopes,
+            token: fixture.token,
+            orgs: fixture.orgs,
+            repos: Mutex::new(fixture.repos),
+            outside_collaborators: Mutex::new(fixture.outside_collaborators),
+            teams: Mutex::new(fixture.teams),
+        }
+    }
+
+    /// Loads a JSON fixture from disk.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AppError::Config(format!("failed to read fixture {}: {e}", path.display()))
+        })?;
+        let fixture = serde_json::from_str::<Fixture>(&content)
+            .map_err(|e| AppError::Config(format!("invalid fixture {}: {e}", path.display())))?;
+        Ok(Self::new(fixture))
+    }
+
+    /// A token this fake accepts: the fixture's, or any placeholder.
+    pub fn accepted_token(&self) -> &str {
+        self.token.as_deref().unwrap_or("demo")
+    }
+
+    /// Current collaborator logins of a repository, for assertions.
+    pub fn collaborator_logins(&self, owner: &str, repo: &str) -> Vec<String> {
+        self.with_repo(owner, repo, |fixture| {
+            fixture
+                .collaborators
+                .iter()
+                .map(|c| c.login.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Current deploy key IDs of a repository, for assertions.
+    pub fn deploy_key_ids(&self, owner: &str, repo: &str) -> Vec<u64> {
+        self.with_repo(owner, repo, |fixture| {
+            fixture.deploy_keys.iter().map(|k| k.id).collect()
+        })
+        .unwrap_or_default()
+    }
+
+    fn check_token(&self, token: &str) -> Result<(), AppError> {
+        match &self.token {
+            Some(expected) if expected != token => Err(AppError::Auth),
+            _ => Ok(()),
+        }
+    }
+
+    fn with_team<T>(
+        &self,
+        org: &str,
+        team: &str,
+        f: impl FnOnce(&mut FixtureTeam) -> T,
+    ) -> Option<T> {
+        let mut teams = self.teams.lock().unwrap_or_else(|e| e.into_inner());
+        teams
+            .get_mut(org)?
+            .iter_mut()
+            .find(|t| t.slug == team)
+            .map(f)
+    }
+
+    fn with_repo<T>(
+        &self,
+        owner: &str,
+        repo: &str,
+        f: impl FnOnce(&mut FixtureRepo) -> T,
+    ) -> Option<T> {
+        let mut repos = self.repos.lock().unwrap_or_else(|e| e.into_inner());
+        repos
+            .iter_mut()
+            .find(|r| r.repo.owner.login == owner && r.repo.name == repo)
+            .map(f)
+    }
+}
+
+#[async_trait]
+impl GitHubApi for FakeGitHub {
+    async fn fetch_token_identity(&self, token: &str) -> Result<TokenIdentity, AppError> {
+        self.check_token(token)?;
+        Ok(TokenIdentity {
+            user: GitHubUser {
+                login: self.viewer.clone(),
+            },
+            scopes: self.scopes.clone(),
+        })
+    }
+
+    async fn fetch_owned_repos(
+        &self,
+        token: &str,
+        options: &RepoFilterOptions,
+    ) -> Result<Vec<Repository>, AppError> {
+        self.check_token(token)?;
+        let repos = self.repos.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(repos
+            .iter()
+            .map(|r| &r.repo)
+            .filter(|repo| repo.owner.login == self.viewer)
+            .filter(|repo| {
+                !(options.ignore_forks && repo.fork || options.ignore_archived && repo.archived)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn fetch_org_repos(
+        &self,
+        token: &str,
+        org: &str,
+        options: &RepoFilterOptions,
+    ) -> Result<Vec<Repository>, AppError> {
+        self.check_token(token)?;
+        let repos = self.repos.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(repos
+            .iter()
+            .map(|r| &r.repo)
+            .filter(|repo| repo.owner.login == org)
+            .filter(|repo| {
+                !(options.ignore_forks && repo.fork || options.ignore_archived && repo.archived)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn fetch_repo_collaborators(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<CollaboratorFetchOutcome, AppError> {
+        self.check_token(token)?;
+        self.with_repo(owner, repo, |fixture| {
+            if fixture.forbidden {
+                CollaboratorFetchOutcome::Forbidden
+            } else {
+                CollaboratorFetchOutcome::Success(fixture.collaborators.clone())
+            }
+        })
+        .ok_or_else(|| AppError::Upstream(format!("repository not found: {owner}/{repo}")))
+    }
+
+    async fn fetch_user_orgs(&self, token: &str) -> Result<Vec<Owner>, AppError> {
+        self.check_token(token)?;
+        Ok(self.orgs.clone())
+    }
+
+    async fn fetch_org_sso_status(&self, token: &str, _org: &str) -> Result<SsoStatus, AppError> {
+        self.check_token(token)?;
+        Ok(SsoStatus::Authorized)
+    }
+
+    async fn fetch_rate_limit(&self, token: &str) -> Result<RateLimitBucket, AppError> {
+        self.check_token(token)?;
+        Ok(RateLimitBucket {
+            limit: 5000,
+            remaining: 5000,
+            used: 0,
+            reset: Utc::now().timestamp() + 3600,
+        })
+    }
+
+    async fn fetch_effective_permission(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<Option<CollaboratorPermission>, AppError> {
+        self.check_token(token)?;
+        Ok(self.with_repo(owner, repo, |fixture| {
+            let (permission, role_name) = if username == fixture.repo.owner.login {
+                ("admin".to_string(), Some("admin".to_string()))
+            } else {
+                match fixture.collaborators.iter().find(|c| c.login == username) {
+                    Some(c) => (c.permission_label().to_string(), c.role_name.clone()),
+                    None => ("none".to_string(), None),
+                }
+            };
+            CollaboratorPermission {
+                permission,
+                role_name,
+                user: GitHubUser {
+                    login: username.to_string(),
+                },
+            }
+        }))
+    }
+
+    async fn repo_exists_for_owner(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<bool, AppError> {
+        self.check_token(token)?;
+        Ok(self.with_repo(owner, repo, |_| ()).is_some())
+    }
+
+    async fn remove_collaborator(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError> {
+        self.check_token(token)?;
+        let status = self.with_repo(owner, repo, |fixture| {
+            if let Some(code) = fixture.remove_status {
+                return StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            let before = fixture.collaborators.len();
+            fixture.collaborators.retain(|c| c.login != username);
+            if fixture.collaborators.len() < before {
+                StatusCode::NO_CONTENT
+            } else {
+                StatusCode::NOT_FOUND
+            }
+        });
+        Ok(status.unwrap_or(StatusCode::NOT_FOUND))
+    }
+
+    async fn fetch_deploy_keys(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<Vec<DeployKey>>, AppError> {
+        self.check_token(token)?;
+        Ok(self
+            .with_repo(owner, repo, |fixture| {
+                (!fixture.forbidden).then(|| fixture.deploy_keys.clone())
+            })
+            .flatten())
+    }
+
+    async fn remove_deploy_key(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+        key_id: u64,
+    ) -> Result<StatusCode, AppError> {
+        self.check_token(token)?;
+        let status = self.with_repo(owner, repo, |fixture| {
+            let before = fixture.deploy_keys.len();
+            fixture.deploy_keys.retain(|k| k.id != key_id);
+            if fixture.deploy_keys.len() < before {
+                StatusCode::NO_CONTENT
+            } else {
+                StatusCode::NOT_FOUND
+            }
+        });
+        Ok(status.unwrap_or(StatusCode::NOT_FOUND))
+    }
+
+    async fn fetch_outside_collaborators(
+        &self,
+        token: &str,
+        org: &str,
+    ) -> Result<Vec<GitHubUser>, AppError> {
+        self.check_token(token)?;
+        let outside = self
+            .outside_collaborators
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        Ok(outside
+            .get(org)
+            .into_iter()
+            .flatten()
+            .map(|login| GitHubUser {
+                login: login.clone(),
+            })
+            .collect())
+    }
+
+    async fn remove_outside_collaborator(
+        &self,
+        token: &str,
+        org: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError> {
+        self.check_token(token)?;
+        let mut outside = self
+            .outside_collaborators
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let Some(logins) = outside.get_mut(org) else {
+            return Ok(StatusCode::NOT_FOUND);
+        };
+        if !logins.iter().any(|login| login == username) {
+            // GitHub answers 422 for organization members.
+            return Ok(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+        logins.retain(|login| login != username);
+
+        let mut repos = self.repos.lock().unwrap_or_else(|e| e.into_inner());
+        for fixture in repos.iter_mut().filter(|r| r.repo.owner.login == org) {
+            fixture.collaborators.retain(|c| c.login != username);
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    async fn fetch_org_teams(&self, token: &str, org: &str) -> Result<Vec<Team>, AppError> {
+        self.check_token(token)?;
+        let teams = self.teams.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(teams
+            .get(org)
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, team)| Team {
+                id: i as u64 + 1,
+                slug: team.slug.clone(),
+                name: team.name.clone(),
+                parent: team.parent.clone().map(|slug| TeamParent { slug }),
+            })
+            .collect())
+    }
+
+    async fn fetch_team_members(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        role: TeamRole,
+    ) -> Result<Vec<GitHubUser>, AppError> {
+        self.check_token(token)?;
+        let logins = self
+            .with_team(org, team, |fixture| match role {
+                TeamRole::Maintainer => fixture.maintainers.clone(),
+                TeamRole::Member => fixture.members.clone(),
+            })
+            .ok_or_else(|| AppError::Upstream(format!("team not found: {org}/{team}")))?;
+        Ok(logins
+            .into_iter()
+            .map(|login| GitHubUser { login })
+            .collect())
+    }
+
+    async fn fetch_team_repos(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+    ) -> Result<Vec<TeamRepository>, AppError> {
+        self.check_token(token)?;
+        let team_repos = self
+            .with_team(org, team, |fixture| fixture.repos.clone())
+            .ok_or_else(|| AppError::Upstream(format!("team not found: {org}/{team}")))?;
+        Ok(team_repos
+            .into_iter()
+            .filter_map(|team_repo| {
+                let repo = self.with_repo(org, &team_repo.name, |fixture| fixture.repo.clone())?;
+                Some(TeamRepository {
+                    repo,
+                    permissions: team_repo.permissions,
+                    role_name: team_repo.role_name,
+                })
+            })
+            .collect())
+    }
+
+    async fn remove_team_repo(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        repo: &str,
+    ) -> Result<StatusCode, AppError> {
+        self.check_token(token)?;
+        // GitHub answers 204 whether or not the team had access.
+        let status = self.with_team(org, team, |fixture| {
+            fixture.repos.retain(|r| r.name != repo);
+            StatusCode::NO_CONTENT
+        });
+        Ok(status.unwrap_or(StatusCode::NOT_FOUND))
+    }
+
+    async fn remove_team_member(
+        &self,
+        token: &str,
+        org: &str,
+        team: &str,
+        username: &str,
+    ) -> Result<StatusCode, AppError> {
+        self.check_token(token)?;
+        let status = self.with_team(org, team, |fixture| {
+            let before = fixture.members.len() + fixture.maintainers.len();
+            fixture.members.retain(|login| login != username);
+            fixture.maintainers.retain(|login| login != username);
+            if fixture.members.len() + fixture.maintainers.len() < before {
+                StatusCode::NO_CONTENT
+            } else {
+                StatusCode::NOT_FOUND
+            }
+        });
+        Ok(status.unwrap_or(StatusCode::NOT_FOUND))
+    }
+}
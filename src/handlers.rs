@@ -1,28 +1,39 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
 
 use askama::Template;
 use axum::{
     Json,
-    extract::{Extension, Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Redirect},
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{Html, IntoResponse, Redirect, Response},
 };
 use axum_extra::extract::PrivateCookieJar;
 use serde::{Deserialize, Serialize};
-use tracing::{error, info, warn};
-use url::Url;
+use tracing::{error, info, instrument, warn};
 
 use crate::{
-    AppState, auth,
+    AppState,
+    audit::{AuditEvent, AuditOutcome},
+    auth,
     error::AppError,
-    github::GitHubClient,
+    idempotency::{self, Begin, StoredResponse},
+    jobs::{Job, JobAccepted},
+    metrics,
     models::{
-        DashboardQuery, GitHubAccessTokenResponse, OAuthCallbackQuery, RemoveFailure,
-        RemoveRequest, RemoveResponse, RemoveSuccess, SessionData,
+        AuthMethod, DashboardQuery, DeployKeyRemoveRequest, DeployKeyRemoveResponse,
+        GitHubAccessTokenResponse, LandingQuery, OAuthCallbackQuery, OrgRemoveRequest,
+        OutsideCollaborator, ProposalRequest, RemoveItem, RemoveRequest, RemoveResponse,
+        RepoDeployKeys, RepoFilterOptions, RepoWithCollaborators, SessionData, SsoStatus,
     },
+    proposals::{Proposal, ProposalStatus},
+    teams::{self, TeamAudit},
     utils,
+    workspaces::{Role, Workspace, WorkspaceAccess, WorkspaceMembership},
 };
 
+const REVOCATION_FAILED_NOTICE: &str = "You are signed out, but GitHub did not confirm that the \
+    access token was revoked. Revoke it under Settings > Applications > Authorized OAuth Apps.";
+
 #[derive(Template)]
 #[template(path = "dashboard.html")]
 struct DashboardTemplate {
@@ -30,11 +41,57 @@ struct DashboardTemplate {
     csrf_token: String,
     ignore_forks: bool,
     ignore_archived: bool,
+    app_enabled: bool,
+    installation_view: bool,
+    /// Workspaces the user belongs to, linked to their team views.
+    workspaces: Vec<String>,
+    demo_mode: bool,
+}
+
+#[derive(Template)]
+#[template(path = "teams.html")]
+struct TeamsTemplate {
+    workspace: String,
+    org: String,
+    csrf_token: String,
+    can_remove: bool,
+    teams: Vec<TeamAudit>,
 }
 
 #[derive(Template)]
 #[template(path = "index.html")]
-struct LandingTemplate;
+struct LandingTemplate {
+    notice: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "diagnostics.html")]
+struct DiagnosticsTemplate {
+    user_login: String,
+    granted_scopes: String,
+    header_scopes: String,
+    missing_scopes: Vec<&'static str>,
+    orgs: Vec<OrgSsoRow>,
+    orgs_error: Option<String>,
+    rate_limit: Option<RateLimitRow>,
+    rate_limit_error: Option<String>,
+    forbidden_repos: Vec<String>,
+    audit_error: Option<String>,
+}
+
+struct OrgSsoRow {
+    org: String,
+    status: &'static str,
+    detail: String,
+    authorize_url: Option<String>,
+}
+
+struct RateLimitRow {
+    limit: u64,
+    remaining: u64,
+    used: u64,
+    resets_in_secs: i64,
+}
 
 #[derive(Debug, Clone, Serialize)]
 struct DashboardRow {
@@ -53,11 +110,20 @@ struct OAuthTokenExchangeRequest<'a> {
     state: &'a str,
 }
 
-pub async fn index(jar: PrivateCookieJar) -> Result<impl IntoResponse, AppError> {
-    match auth::read_session(&jar) {
+#[instrument(skip_all)]
+pub async fn index(
+    State(state): State<AppState>,
+    jar: PrivateCookieJar,
+    Query(query): Query<LandingQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    match auth::read_session(&jar, &state.sessions) {
         Ok(Some(_)) => Ok(Redirect::to("/dashboard").into_response()),
         Ok(None) | Err(_) => {
-            let template = LandingTemplate;
+            let notice = match query.revocation.as_deref() {
+                Some("failed") => Some(REVOCATION_FAILED_NOTICE.to_string()),
+                _ => None,
+            };
+            let template = LandingTemplate { notice };
             Ok(Html(template.render()?).into_response())
         }
     }
@@ -67,11 +133,31 @@ pub async fn health() -> impl IntoResponse {
     "ok"
 }
 
+pub async fn metrics() -> impl IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        metrics::metrics().encode(),
+    )
+}
+
+#[instrument(skip_all)]
 pub async fn auth_login(
     State(state): State<AppState>,
     jar: PrivateCookieJar,
 ) -> Result<(PrivateCookieJar, Redirect), AppError> {
-    if auth::read_session(&jar)?.is_some() {
+    if auth::read_session(&jar, &state.sessions)?.is_some() {
+        return Ok((jar, Redirect::to("/dashboard")));
+    }
+    if let Some(demo) = &state.demo_session {
+        // Demo sign-in: a real session, so CSRF protection still applies.
+        let session_id = state.sessions.create(SessionData {
+            csrf_token: utils::random_token(32),
+            ..demo.clone()
+        })?;
+        let jar = auth::write_session(jar, &session_id, state.config.base_url.scheme() == "https");
         return Ok((jar, Redirect::to("/dashboard")));
     }
 
@@ -85,21 +171,23 @@ pub async fn auth_login(
         .join("auth/callback")
         .map_err(|e| AppError::Config(format!("invalid callback URL: {e}")))?;
 
-    let authorization_url = Url::parse_with_params(
-        "https://github.com/login/oauth/authorize",
-        &[
-            ("client_id", state.config.github_client_id.as_str()),
-            ("redirect_uri", redirect_uri.as_str()),
-            ("scope", "repo read:org"),
-            ("state", oauth_state.as_str()),
-        ],
-    )
-    .map_err(|_e| AppError::Internal)?;
+    let mut authorization_url = state
+        .config
+        .github_web_url
+        .join("login/oauth/authorize")
+        .map_err(|e| AppError::Config(format!("invalid GitHub OAuth URL: {e}")))?;
+    authorization_url.query_pairs_mut().extend_pairs(&[
+        ("client_id", state.config.github_client_id.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("scope", "repo read:org"),
+        ("state", oauth_state.as_str()),
+    ]);
 
     info!("starting github oauth flow");
     Ok((jar, Redirect::to(authorization_url.as_str())))
 }
 
+#[instrument(skip_all)]
 pub async fn auth_callback(
     State(state): State<AppState>,
     jar: PrivateCookieJar,
@@ -143,10 +231,16 @@ pub async fn auth_callback(
         state: &expected_state,
     };
 
+    let token_url = state
+        .config
+        .github_web_url
+        .join("login/oauth/access_token")
+        .map_err(|e| AppError::Config(format!("invalid GitHub OAuth URL: {e}")))?;
+
     let token_response = state
-        .github
+        .github_client
         .http
-        .post("https://github.com/login/oauth/access_token")
+        .post(token_url)
         .header("Accept", "application/json")
         .form(&token_payload)
         .send()
@@ -172,7 +266,7 @@ pub async fn auth_callback(
         )));
     }
 
-    let access_token = token.access_token.ok_or_else(|| AppError::Auth)?;
+    let access_token = token.access_token.ok_or(AppError::Auth)?;
     let token_type = token.token_type.unwrap_or_default();
     let scopes = token.scope.unwrap_or_default();
 
@@ -180,32 +274,49 @@ pub async fn auth_callback(
         return Err(AppError::Auth);
     }
 
-    if !auth::has_required_scopes(&scopes) {
-        return Err(AppError::BadRequest(
-            "OAuth scopes are insufficient. Required scopes: repo, read:org".to_string(),
-        ));
-    }
+    auth::ensure_required_scopes(&scopes)?;
 
-    let user = state.github.fetch_authenticated_user(&access_token).await?;
+    let user = state
+        .github_client
+        .fetch_authenticated_user(&access_token)
+        .await?;
+    if !state.config.is_user_allowed(&user.login) {
+        warn!(
+            user = user.login,
+            "sign-in refused, user is not on the allowlist"
+        );
+        return Err(AppError::Forbidden);
+    }
 
-    let session = SessionData {
+    // A fresh session ID and CSRF token on every login, so nothing issued
+    // before authentication survives it.
+    if let Some(previous) = auth::session_id(&jar) {
+        state.sessions.revoke(&previous)?;
+    }
+    let session_id = state.sessions.create(SessionData {
         access_token,
         user_login: user.login,
         csrf_token: utils::random_token(32),
-    };
+        granted_scopes: Some(scopes),
+    })?;
 
-    let jar = auth::write_session(jar, &session, secure_cookie)?;
+    let jar = auth::write_session(jar, &session_id, secure_cookie);
     let jar = auth::clear_oauth_state(jar, secure_cookie);
 
     info!("github oauth completed successfully");
     Ok((jar, Redirect::to("/dashboard")))
 }
 
+#[instrument(skip_all)]
 pub async fn dashboard(
     State(state): State<AppState>,
     Extension(session): Extension<SessionData>,
     Query(query): Query<DashboardQuery>,
 ) -> Result<Html<String>, AppError> {
+    let _timer = metrics::metrics()
+        .dashboard_render_duration
+        .with_label_values(&["owned"])
+        .start_timer();
     let ignore_forks = query.ignore_forks;
     let ignore_archived = query.ignore_archived;
 
@@ -219,11 +330,212 @@ pub async fn dashboard(
         )
         .await?;
 
-    let rows = data
-        .into_iter()
+    let template = DashboardTemplate {
+        rows: dashboard_rows(data, &session.user_login),
+        csrf_token: session.csrf_token,
+        ignore_forks,
+        ignore_archived,
+        app_enabled: state.github_app.is_some()
+            && !admin_orgs(&state, &session.user_login).is_empty(),
+        installation_view: false,
+        workspaces: workspace_names(&state, &session.user_login),
+        demo_mode: state.config.demo_fixture.is_some(),
+    };
+    let rendered = template.render()?;
+    Ok(Html(rendered))
+}
+
+/// Installation tokens see every repository the App is installed on, so this
+/// view is limited to workspace admins and to the organizations of the
+/// workspaces they administer.
+#[instrument(skip_all)]
+pub async fn app_dashboard(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Html<String>, AppError> {
+    let Some(app) = state.github_app.as_ref() else {
+        return Err(AppError::BadRequest(
+            "GitHub App mode is not configured".to_string(),
+        ));
+    };
+    let orgs = admin_orgs(&state, &session.user_login);
+    if orgs.is_empty() {
+        warn!(
+            user = session.user_login,
+            "installation dashboard refused, not a workspace admin"
+        );
+        return Err(AppError::Forbidden);
+    }
+    let _timer = metrics::metrics()
+        .dashboard_render_duration
+        .with_label_values(&["installations"])
+        .start_timer();
+
+    let ignore_forks = query.ignore_forks;
+    let ignore_archived = query.ignore_archived;
+
+    let data = state
+        .github_client
+        .fetch_installation_repos_with_collaborators(
+            app,
+            &session.user_login,
+            &orgs,
+            query.into(),
+            state.config.max_concurrency,
+        )
+        .await?;
+
+    let template = DashboardTemplate {
+        rows: dashboard_rows(data, &session.user_login),
+        csrf_token: session.csrf_token,
+        ignore_forks,
+        ignore_archived,
+        app_enabled: true,
+        installation_view: true,
+        workspaces: workspace_names(&state, &session.user_login),
+        demo_mode: false,
+    };
+    let rendered = template.render()?;
+    Ok(Html(rendered))
+}
+
+#[instrument(skip_all)]
+pub async fn diagnostics(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+) -> Result<Html<String>, AppError> {
+    let token = session.access_token.as_str();
+    let options = RepoFilterOptions {
+        ignore_forks: false,
+        ignore_archived: false,
+    };
+
+    let (identity, orgs, rate_limit, audit) = futures::join!(
+        state.github.fetch_token_identity(token),
+        state.github.fetch_user_orgs(token),
+        state.github.fetch_rate_limit(token),
+        state.github.fetch_repo_audit(
+            token,
+            &session.user_login,
+            options,
+            state.config.max_concurrency,
+        ),
+    );
+
+    let header_scopes = identity?.scopes;
+    let effective_scopes = header_scopes
+        .as_deref()
+        .or(session.granted_scopes.as_deref());
+    let missing_scopes = effective_scopes
+        .map(auth::missing_scopes)
+        .unwrap_or_default();
+
+    let (orgs, orgs_error) = match orgs {
+        Ok(orgs) => {
+            let statuses = futures::future::join_all(
+                orgs.iter()
+                    .map(|org| state.github.fetch_org_sso_status(token, &org.login)),
+            )
+            .await;
+            let rows = orgs
+                .into_iter()
+                .zip(statuses)
+                .map(|(org, status)| org_sso_row(org.login, status))
+                .collect();
+            (rows, None)
+        }
+        Err(err) => {
+            warn!(error = %err, "diagnostics: organization lookup failed");
+            (Vec::new(), Some(err.to_string()))
+        }
+    };
+
+    let (rate_limit, rate_limit_error) = match rate_limit {
+        Ok(bucket) => (
+            Some(RateLimitRow {
+                limit: bucket.limit,
+                remaining: bucket.remaining,
+                used: bucket.used,
+                resets_in_secs: (bucket.reset - chrono::Utc::now().timestamp()).max(0),
+            }),
+            None,
+        ),
+        Err(err) => (None, Some(err.to_string())),
+    };
+
+    let (forbidden_repos, audit_error) = match audit {
+        Ok(audit) => (
+            audit
+                .forbidden
+                .into_iter()
+                .map(|repo| format!("{}/{}", repo.owner.login, repo.name))
+                .collect(),
+            None,
+        ),
+        Err(err) => (Vec::new(), Some(err.to_string())),
+    };
+
+    let template = DiagnosticsTemplate {
+        user_login: session.user_login,
+        granted_scopes: session
+            .granted_scopes
+            .unwrap_or_else(|| "unknown".to_string()),
+        header_scopes: header_scopes
+            .unwrap_or_else(|| "not reported (fine-grained token)".to_string()),
+        missing_scopes,
+        orgs,
+        orgs_error,
+        rate_limit,
+        rate_limit_error,
+        forbidden_repos,
+        audit_error,
+    };
+    Ok(Html(template.render()?))
+}
+
+fn org_sso_row(org: String, status: Result<SsoStatus, AppError>) -> OrgSsoRow {
+    match status {
+        Ok(SsoStatus::Authorized) => OrgSsoRow {
+            org,
+            status: "ok",
+            detail: "accessible".to_string(),
+            authorize_url: None,
+        },
+        Ok(SsoStatus::Required { authorize_url }) => OrgSsoRow {
+            org,
+            status: "bad",
+            detail: "SAML SSO authorization required".to_string(),
+            authorize_url,
+        },
+        Ok(SsoStatus::Unavailable(status)) => OrgSsoRow {
+            org,
+            status: "warn",
+            detail: format!("not accessible: {status}"),
+            authorize_url: None,
+        },
+        Err(err) => OrgSsoRow {
+            org,
+            status: "warn",
+            detail: err.to_string(),
+            authorize_url: None,
+        },
+    }
+}
+
+/// Flattens audited repositories into table rows. `/remove` only acts on repos
+/// owned by the viewer, so rows for other owners are shown by full name and
+/// never offered for removal.
+fn dashboard_rows(data: Vec<RepoWithCollaborators>, viewer: &str) -> Vec<DashboardRow> {
+    data.into_iter()
         .flat_map(|repo_row| {
-            let repo_name = repo_row.repo.name;
-            let can_remove = repo_row.can_remove;
+            let owned = repo_row.repo.owner.login == viewer;
+            let repo_name = if owned {
+                repo_row.repo.name
+            } else {
+                format!("{}/{}", repo_row.repo.owner.login, repo_row.repo.name)
+            };
+            let can_remove = owned && repo_row.can_remove;
             repo_row.collaborators.into_iter().map(move |c| {
                 let permission = c.permission_label().to_string();
                 DashboardRow {
@@ -234,176 +546,745 @@ pub async fn dashboard(
                 }
             })
         })
-        .collect::<Vec<_>>();
-
-    let template = DashboardTemplate {
-        rows,
-        csrf_token: session.csrf_token,
-        ignore_forks,
-        ignore_archived,
-    };
-    let rendered = template.render()?;
-    Ok(Html(rendered))
+        .collect()
 }
 
+#[instrument(skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(method): Extension<AuthMethod>,
     jar: PrivateCookieJar,
 ) -> Result<(PrivateCookieJar, Redirect), AppError> {
     let secure_cookie = state.config.base_url.scheme() == "https";
+    if let Some(session_id) = auth::session_id(&jar) {
+        state.sessions.revoke(&session_id)?;
+    }
+    state.audit.record(AuditEvent::new(
+        &session.user_login,
+        "logout",
+        AuditOutcome::Success,
+    ));
+
+    let redirect = revoke_oauth_grant(&state, &session, method).await;
     let jar = auth::clear_session(jar, secure_cookie);
-    Ok((jar, Redirect::to("/")))
+    Ok((jar, redirect))
 }
 
-pub async fn remove_collaborators(
+#[instrument(skip_all)]
+pub async fn logout_all(
     State(state): State<AppState>,
     Extension(session): Extension<SessionData>,
-    Json(payload): Json<RemoveRequest>,
-) -> Result<(StatusCode, Json<RemoveResponse>), AppError> {
-    if payload.items.is_empty() {
-        return Err(AppError::BadRequest("items must not be empty".to_string()));
+    Extension(method): Extension<AuthMethod>,
+    jar: PrivateCookieJar,
+) -> Result<(PrivateCookieJar, Redirect), AppError> {
+    let secure_cookie = state.config.base_url.scheme() == "https";
+    let revoked = state.sessions.revoke_user(&session.user_login)?;
+    info!(
+        user = session.user_login,
+        revoked, "signed out all sessions"
+    );
+    state.audit.record(
+        AuditEvent::new(&session.user_login, "logout_all", AuditOutcome::Success)
+            .with_detail(format!("{revoked} session(s) revoked")),
+    );
+
+    let redirect = revoke_oauth_grant(&state, &session, method).await;
+    let jar = auth::clear_session(jar, secure_cookie);
+    Ok((jar, redirect))
+}
+
+/// Revokes the session's OAuth token at GitHub when configured to, and picks
+/// the landing page redirect that reports the outcome. Personal access tokens
+/// were not issued by this app and are left alone.
+async fn revoke_oauth_grant(
+    state: &AppState,
+    session: &SessionData,
+    method: AuthMethod,
+) -> Redirect {
+    if !state.config.revoke_token_on_logout || method != AuthMethod::Session {
+        return Redirect::to("/");
     }
 
-    let mut success = Vec::new();
-    let mut failed = Vec::new();
-
-    let mut repos_seen = HashSet::new();
-    for item in &payload.items {
-        if item.repo.trim().is_empty() || item.username.trim().is_empty() {
-            failed.push(RemoveFailure {
-                repo: item.repo.clone(),
-                username: item.username.clone(),
-                reason: "repo and username must be non-empty".to_string(),
-            });
-            continue;
+    let result = state
+        .github_client
+        .revoke_oauth_token(
+            &state.config.github_client_id,
+            &state.config.github_client_secret,
+            &session.access_token,
+        )
+        .await;
+
+    let failure = match result {
+        Ok(StatusCode::NO_CONTENT) => None,
+        Ok(StatusCode::NOT_FOUND) => {
+            info!(user = session.user_login, "oauth token was already invalid");
+            None
+        }
+        Ok(status) => Some(format!("unexpected response status: {status}")),
+        Err(err) => Some(err.to_string()),
+    };
+
+    match failure {
+        None => {
+            state.audit.record(AuditEvent::new(
+                &session.user_login,
+                "oauth_token_revoke",
+                AuditOutcome::Success,
+            ));
+            Redirect::to("/")
+        }
+        Some(detail) => {
+            warn!(
+                user = session.user_login,
+                detail, "oauth token revocation failed"
+            );
+            state.audit.record(
+                AuditEvent::new(
+                    &session.user_login,
+                    "oauth_token_revoke",
+                    AuditOutcome::Failure,
+                )
+                .with_detail(detail),
+            );
+            Redirect::to("/?revocation=failed")
         }
-        repos_seen.insert(item.repo.clone());
     }
+}
+
+#[instrument(skip_all)]
+pub async fn remove_collaborators(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    headers: HeaderMap,
+    Json(payload): Json<RemoveRequest>,
+) -> Result<Response, AppError> {
+    remove_batch(&state, session, &headers, payload, "/jobs").await
+}
 
-    let mut ownership_cache: HashMap<String, bool> = HashMap::new();
-    let mut admin_cache: HashMap<String, bool> = HashMap::new();
+/// Handles a `/remove` request, replaying the stored response when an
+/// `Idempotency-Key` was already used for the same body. A keyed batch runs in
+/// its own task, so it finishes and is stored even if the client disconnects.
+pub(crate) async fn remove_batch(
+    state: &AppState,
+    session: SessionData,
+    headers: &HeaderMap,
+    payload: RemoveRequest,
+    jobs_path: &'static str,
+) -> Result<Response, AppError> {
+    let Some(key) = idempotency::key(headers)? else {
+        let stored = run_batch(state, session, payload, jobs_path).await?;
+        return Ok((stored.status, Json(stored.body)).into_response());
+    };
 
-    for repo in repos_seen {
-        let owned = match state
-            .github
-            .repo_exists_for_owner(&session.access_token, &session.user_login, &repo)
+    let fingerprint = idempotency::fingerprint(&payload)?;
+    let stored = match state
+        .idempotency
+        .begin(&session.user_login, &key, fingerprint)?
+    {
+        Begin::Replay(stored) => return Ok(replayed(stored)),
+        Begin::Wait(pending) => return Ok(replayed(idempotency::wait(pending).await?)),
+        Begin::New(reservation) => {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let stored = run_batch(&state, session, payload, jobs_path).await?;
+                reservation.complete(stored.clone());
+                Ok::<_, AppError>(stored)
+            })
             .await
-        {
-            Ok(value) => value,
-            Err(err) => {
-                warn!(repo, error = %err, "ownership validation failed");
-                false
-            }
+            .map_err(|_| AppError::Internal)??
+        }
+    };
+    Ok((stored.status, Json(stored.body)).into_response())
+}
+
+fn replayed(stored: StoredResponse) -> Response {
+    (
+        stored.status,
+        [(idempotency::REPLAYED_HEADER, "true")],
+        Json(stored.body),
+    )
+        .into_response()
+}
+
+/// Runs a removal batch inline, or queues it as a job whose progress is
+/// served under `jobs_path` when the request asks for `background`.
+async fn run_batch(
+    state: &AppState,
+    session: SessionData,
+    payload: RemoveRequest,
+    jobs_path: &str,
+) -> Result<StoredResponse, AppError> {
+    if payload.background {
+        state.service.check_batch(&payload.items)?;
+        let job_id = state.jobs.submit(&state.service, session, payload.items);
+        let accepted = JobAccepted {
+            status_url: format!("{jobs_path}/{job_id}"),
+            job_id,
         };
-        ownership_cache.insert(repo.clone(), owned);
+        return Ok(StoredResponse {
+            status: StatusCode::ACCEPTED,
+            body: serde_json::to_value(accepted)?,
+        });
+    }
 
-        let is_admin = if owned {
-            match state
-                .github
-                .fetch_effective_permission(
-                    &session.access_token,
-                    &session.user_login,
-                    &repo,
-                    &session.user_login,
+    let response = state.service.remove(&session, payload.items).await?;
+    Ok(StoredResponse {
+        status: StatusCode::OK,
+        body: serde_json::to_value(response)?,
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn job_status(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    state
+        .jobs
+        .get(&id, &session.user_login)
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("job not found: {id}")))
+}
+
+/// Deploy keys of every owned repository.
+#[instrument(skip_all)]
+pub async fn deploy_keys(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Json<Vec<RepoDeployKeys>>, AppError> {
+    state
+        .github
+        .fetch_repos_with_deploy_keys(
+            &session.access_token,
+            query.into(),
+            state.config.max_concurrency,
+        )
+        .await
+        .map(Json)
+}
+
+#[instrument(skip_all)]
+pub async fn remove_deploy_keys(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Json(payload): Json<DeployKeyRemoveRequest>,
+) -> Result<Json<DeployKeyRemoveResponse>, AppError> {
+    state
+        .service
+        .remove_deploy_keys(&session, payload.items)
+        .await
+        .map(Json)
+}
+
+/// Workspaces the session user belongs to, with their role in each.
+#[instrument(skip_all)]
+pub async fn list_workspaces(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+) -> Json<Vec<WorkspaceMembership>> {
+    let memberships = state
+        .config
+        .workspaces
+        .iter()
+        .filter_map(|workspace| {
+            workspace
+                .role_of(&session.user_login)
+                .map(|role| WorkspaceMembership {
+                    name: workspace.name.clone(),
+                    org: workspace.org.clone(),
+                    role,
+                })
+        })
+        .collect();
+    Json(memberships)
+}
+
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_audit(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Json<Vec<RepoWithCollaborators>>, AppError> {
+    workspace_rows(&state, &session, &access, query)
+        .await
+        .map(Json)
+}
+
+/// The workspace audit as CSV, one row per collaborator.
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_export(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Response, AppError> {
+    let rows = workspace_rows(&state, &session, &access, query).await?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(["repo", "username", "permission", "role_name"])
+        .map_err(|_| AppError::Internal)?;
+    for row in &rows {
+        for collaborator in &row.collaborators {
+            writer
+                .write_record(
+                    [
+                        row.repo.name.as_str(),
+                        collaborator.login.as_str(),
+                        collaborator.permission_label(),
+                        collaborator.role_name.as_deref().unwrap_or_default(),
+                    ]
+                    .map(csv_cell),
                 )
-                .await
-            {
-                Ok(Some(permission)) => GitHubClient::is_admin_permission(&permission),
-                Ok(None) => false,
-                Err(err) => {
-                    warn!(repo, error = %err, "admin check failed");
-                    false
-                }
+                .map_err(|_| AppError::Internal)?;
+        }
+    }
+    let body = writer.into_inner().map_err(|_| AppError::Internal)?;
+
+    state.audit.record(
+        AuditEvent::new(
+            &session.user_login,
+            "workspace_export",
+            AuditOutcome::Success,
+        )
+        .with_detail(access.workspace.name.clone()),
+    );
+    let disposition = format!(
+        "attachment; filename=\"{}-collaborators.csv\"",
+        access.workspace.name
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Removes collaborators from the workspace organization's repositories.
+/// Background jobs and idempotency keys are only offered on `/remove`.
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_remove(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Json(payload): Json<RemoveRequest>,
+) -> Result<Json<RemoveResponse>, AppError> {
+    if payload.background {
+        return Err(AppError::BadRequest(
+            "background removals are not supported in workspaces".to_string(),
+        ));
+    }
+    state
+        .service
+        .remove_in(&session, &access.workspace.org, payload.items)
+        .await
+        .map(Json)
+}
+
+/// Outside collaborators of the workspace organization, each with the
+/// repositories they can reach.
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_outside_collaborators(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+) -> Result<Json<Vec<OutsideCollaborator>>, AppError> {
+    let org = &access.workspace.org;
+    let (users, rows) = tokio::try_join!(
+        state
+            .github
+            .fetch_outside_collaborators(&session.access_token, org),
+        state.github.fetch_org_repos_with_collaborators(
+            &session.access_token,
+            &session.user_login,
+            org,
+            RepoFilterOptions {
+                ignore_forks: false,
+                ignore_archived: false,
+            },
+            state.config.max_concurrency,
+        ),
+    )?;
+
+    let outside = users
+        .into_iter()
+        .map(|user| {
+            let repos = rows
+                .iter()
+                .filter(|row| row.collaborators.iter().any(|c| c.login == user.login))
+                .map(|row| row.repo.name.clone())
+                .collect();
+            OutsideCollaborator {
+                login: user.login,
+                repos,
             }
-        } else {
-            false
-        };
+        })
+        .collect();
+    Ok(Json(outside))
+}
+
+/// Removes outside collaborators from the whole workspace organization.
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_remove_outside_collaborators(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Json(payload): Json<OrgRemoveRequest>,
+) -> Result<Json<RemoveResponse>, AppError> {
+    state
+        .service
+        .remove_outside_collaborators(&session, &access.workspace.org, payload.usernames)
+        .await
+        .map(Json)
+}
+
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_submit_proposal(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(method): Extension<AuthMethod>,
+    Extension(access): Extension<WorkspaceAccess>,
+    jar: PrivateCookieJar,
+    Json(payload): Json<ProposalRequest>,
+) -> Result<(StatusCode, Json<Proposal>), AppError> {
+    let session_id = match method {
+        AuthMethod::Session => auth::session_id(&jar),
+        AuthMethod::Token => None,
+    };
+    create_proposal(
+        &state,
+        session,
+        session_id,
+        &access.workspace,
+        payload.items,
+    )
+    .map(|p| (StatusCode::CREATED, Json(p)))
+}
+
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_proposals(
+    State(state): State<AppState>,
+    Extension(access): Extension<WorkspaceAccess>,
+) -> Json<Vec<Proposal>> {
+    Json(state.proposals.pending(&access.workspace.name))
+}
+
+#[instrument(skip_all, fields(workspace = access.workspace.name, proposal = %params.id))]
+pub async fn workspace_approve_proposal(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Path(params): Path<WorkspaceProposalPath>,
+) -> Result<Json<Proposal>, AppError> {
+    run_approved(&state, &session, &params.id, &access.workspace.name)
+        .await
+        .map(Json)
+}
+
+#[instrument(skip_all, fields(workspace = access.workspace.name, proposal = %params.id))]
+pub async fn workspace_reject_proposal(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Path(params): Path<WorkspaceProposalPath>,
+) -> Result<Json<Proposal>, AppError> {
+    decide_proposal(
+        &state,
+        &session,
+        &params.id,
+        &access.workspace.name,
+        ProposalStatus::Rejected,
+    )
+    .await
+    .map(Json)
+}
 
-        admin_cache.insert(repo, is_admin);
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_members(
+    Extension(access): Extension<WorkspaceAccess>,
+) -> Json<BTreeMap<String, Role>> {
+    Json(access.workspace.members)
+}
+
+/// Teams of the workspace organization with their members, maintainers,
+/// nesting and repository permissions.
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_teams(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+) -> Result<Json<Vec<TeamAudit>>, AppError> {
+    teams::audit_org_teams(
+        state.github.as_ref(),
+        &session.access_token,
+        &access.workspace.org,
+        state.config.max_concurrency,
+    )
+    .await
+    .map(Json)
+}
+
+/// The team audit as a page, with remove buttons for removers.
+#[instrument(skip_all, fields(workspace = access.workspace.name))]
+pub async fn workspace_teams_view(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+) -> Result<Html<String>, AppError> {
+    let teams = teams::audit_org_teams(
+        state.github.as_ref(),
+        &session.access_token,
+        &access.workspace.org,
+        state.config.max_concurrency,
+    )
+    .await?;
+
+    let template = TeamsTemplate {
+        workspace: access.workspace.name,
+        org: access.workspace.org,
+        csrf_token: session.csrf_token,
+        can_remove: access.role >= Role::Remover,
+        teams,
+    };
+    Ok(Html(template.render()?))
+}
+
+#[instrument(skip_all, fields(workspace = access.workspace.name, team = params.team, repo = params.repo))]
+pub async fn workspace_remove_team_repo(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Path(params): Path<WorkspaceTeamRepoPath>,
+) -> Result<StatusCode, AppError> {
+    state
+        .service
+        .remove_team_repo(&session, &access.workspace.org, &params.team, &params.repo)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[instrument(skip_all, fields(workspace = access.workspace.name, team = params.team, username = params.username))]
+pub async fn workspace_remove_team_member(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionData>,
+    Extension(access): Extension<WorkspaceAccess>,
+    Path(params): Path<WorkspaceTeamMemberPath>,
+) -> Result<StatusCode, AppError> {
+    state
+        .service
+        .remove_team_member(
+            &session,
+            &access.workspace.org,
+            &params.team,
+            &params.username,
+        )
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct WorkspaceProposalPath {
+    pub id: String,
+}
+
+#[derive(Deserialize)]
+pub struct WorkspaceTeamRepoPath {
+    pub team: String,
+    pub repo: String,
+}
+
+#[derive(Deserialize)]
+pub struct WorkspaceTeamMemberPath {
+    pub team: String,
+    pub username: String,
+}
+
+fn workspace_names(state: &AppState, login: &str) -> Vec<String> {
+    state
+        .config
+        .workspaces
+        .iter()
+        .filter(|workspace| workspace.role_of(login).is_some())
+        .map(|workspace| workspace.name.clone())
+        .collect()
+}
+
+/// Prefixes a cell a spreadsheet would evaluate as a formula with `'`, so an
+/// exported name cannot run as one.
+fn csv_cell(value: &str) -> String {
+    if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_owned()
     }
+}
 
-    for item in payload.items {
-        if item.username == session.user_login {
-            failed.push(RemoveFailure {
-                repo: item.repo,
-                username: item.username,
-                reason: "cannot remove authenticated user".to_string(),
-            });
-            continue;
-        }
+/// Organizations of the workspaces `login` administers.
+fn admin_orgs<'a>(state: &'a AppState, login: &str) -> Vec<&'a str> {
+    state
+        .config
+        .workspaces
+        .iter()
+        .filter(|workspace| workspace.role_of(login) == Some(Role::Admin))
+        .map(|workspace| workspace.org.as_str())
+        .collect()
+}
 
-        if !ownership_cache.get(&item.repo).copied().unwrap_or(false) {
-            failed.push(RemoveFailure {
-                repo: item.repo,
-                username: item.username,
-                reason: "repository is not owned by authenticated user".to_string(),
-            });
-            continue;
-        }
+async fn workspace_rows(
+    state: &AppState,
+    session: &SessionData,
+    access: &WorkspaceAccess,
+    query: DashboardQuery,
+) -> Result<Vec<RepoWithCollaborators>, AppError> {
+    state
+        .github
+        .fetch_org_repos_with_collaborators(
+            &session.access_token,
+            &session.user_login,
+            &access.workspace.org,
+            query.into(),
+            state.config.max_concurrency,
+        )
+        .await
+}
 
-        if !admin_cache.get(&item.repo).copied().unwrap_or(false) {
-            failed.push(RemoveFailure {
-                repo: item.repo,
-                username: item.username,
-                reason: "authenticated user does not have admin permission".to_string(),
-            });
-            continue;
-        }
+fn create_proposal(
+    state: &AppState,
+    session: SessionData,
+    session_id: Option<String>,
+    workspace: &Workspace,
+    items: Vec<RemoveItem>,
+) -> Result<Proposal, AppError> {
+    state.service.check_batch(&items)?;
 
-        info!(
-            repo = item.repo,
-            username = item.username,
-            "attempting collaborator deletion"
-        );
-        let status = match state
+    let proposal = state
+        .proposals
+        .submit(session, session_id, workspace, items);
+    state.audit.record(
+        AuditEvent::new(&proposal.proposer, "removal_propose", AuditOutcome::Success).with_detail(
+            format!(
+                "proposal {} on {}: {} item(s)",
+                proposal.id,
+                proposal.owner,
+                proposal.items.len()
+            ),
+        ),
+    );
+    Ok(proposal)
+}
+
+fn find_proposal(state: &AppState, id: &str, workspace: &str) -> Result<Proposal, AppError> {
+    state
+        .proposals
+        .get(id, workspace)
+        .ok_or_else(|| AppError::NotFound(format!("proposal not found: {id}")))
+}
+
+/// Fails unless the proposer is still signed in: their browser session, if
+/// they proposed from one, is live, and their token still belongs to them.
+/// An approved batch runs with that token, so a proposer who signed out or
+/// had their token revoked must not have it used on their behalf.
+async fn ensure_proposer_signed_in(state: &AppState, proposal: &Proposal) -> Result<(), AppError> {
+    let session_live = match &proposal.session_id {
+        Some(id) => state.sessions.is_live(id, &proposal.proposer)?,
+        None => true,
+    };
+    let token_valid = session_live
+        && match state
             .github
-            .remove_collaborator(
-                &session.access_token,
-                &session.user_login,
-                &item.repo,
-                &item.username,
-            )
+            .fetch_token_identity(&proposal.session.access_token)
             .await
         {
-            Ok(status) => status,
-            Err(err) => {
-                warn!(repo = item.repo, username = item.username, error = %err, "collaborator deletion request failed");
-                failed.push(RemoveFailure {
-                    repo: item.repo,
-                    username: item.username,
-                    reason: "upstream request failed".to_string(),
-                });
-                continue;
-            }
+            Ok(identity) => identity.user.login.eq_ignore_ascii_case(&proposal.proposer),
+            Err(AppError::Auth) => false,
+            Err(err) => return Err(err),
         };
 
-        match status {
-            StatusCode::NO_CONTENT => success.push(RemoveSuccess {
-                repo: item.repo,
-                username: item.username,
-            }),
-            StatusCode::FORBIDDEN => failed.push(RemoveFailure {
-                repo: item.repo,
-                username: item.username,
-                reason: "insufficient permissions".to_string(),
-            }),
-            StatusCode::UNPROCESSABLE_ENTITY => failed.push(RemoveFailure {
-                repo: item.repo,
-                username: item.username,
-                reason: "validation failed or abuse detection triggered".to_string(),
-            }),
-            StatusCode::NOT_FOUND => failed.push(RemoveFailure {
-                repo: item.repo,
-                username: item.username,
-                reason: "collaborator not found".to_string(),
-            }),
-            other => failed.push(RemoveFailure {
-                repo: item.repo,
-                username: item.username,
-                reason: format!("unexpected response status: {other}"),
-            }),
-        }
+    if token_valid {
+        Ok(())
+    } else {
+        warn!(
+            proposal = proposal.id,
+            proposer = proposal.proposer,
+            "proposer is no longer signed in"
+        );
+        Err(AppError::Conflict(format!(
+            "proposer {} has signed out since submitting; they must submit the proposal again",
+            proposal.proposer
+        )))
     }
+}
+
+async fn run_approved(
+    state: &AppState,
+    session: &SessionData,
+    id: &str,
+    workspace: &str,
+) -> Result<Proposal, AppError> {
+    let proposal = decide_proposal(state, session, id, workspace, ProposalStatus::Approved).await?;
 
-    Ok((StatusCode::OK, Json(RemoveResponse { success, failed })))
+    let result = state
+        .service
+        .remove_approved(
+            &proposal.session,
+            &proposal.owner,
+            &session.user_login,
+            proposal.items,
+        )
+        .await?;
+    info!(
+        proposer = proposal.proposer,
+        approver = session.user_login,
+        removed = result.success.len(),
+        failed = result.failed.len(),
+        "approved removal proposal ran"
+    );
+    state
+        .proposals
+        .complete(id, result)
+        .ok_or_else(|| AppError::NotFound(format!("proposal not found: {id}")))
+}
+
+async fn decide_proposal(
+    state: &AppState,
+    session: &SessionData,
+    id: &str,
+    workspace: &str,
+    status: ProposalStatus,
+) -> Result<Proposal, AppError> {
+    let proposal = find_proposal(state, id, workspace)?;
+    state
+        .service
+        .check_approver(
+            session,
+            &proposal.proposer,
+            &proposal.owner,
+            &proposal.items,
+        )
+        .await?;
+    if status == ProposalStatus::Approved {
+        ensure_proposer_signed_in(state, &proposal).await?;
+    }
+
+    let proposal = state.proposals.decide(id, &session.user_login, status)?;
+    let action = match proposal.status {
+        ProposalStatus::Approved => "removal_approve",
+        ProposalStatus::Rejected => "removal_reject",
+        ProposalStatus::Pending => {
+            error!(proposal = id, "proposal still pending after a decision");
+            return Err(AppError::Internal);
+        }
+    };
+    state.audit.record(
+        AuditEvent::new(&session.user_login, action, AuditOutcome::Success)
+            .with_detail(format!("proposal {id} from {}", proposal.proposer)),
+    );
+    Ok(proposal)
 }
@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::http::{HeaderMap, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+
+use crate::error::AppError;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Set on responses replayed from an earlier request with the same key.
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Stored responses are replayed for this long after the first request.
+const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A response kept for replay.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub body: Value,
+}
+
+enum Slot {
+    /// The first request is still running; waiters get its response.
+    InFlight(watch::Receiver<Option<StoredResponse>>),
+    Done(StoredResponse),
+}
+
+struct Entry {
+    fingerprint: [u8; 32],
+    created: Instant,
+    slot: Slot,
+}
+
+/// Remembers responses by user and `Idempotency-Key`, so a retried request
+/// with the same key and body gets the original response instead of running
+/// again. Reusing a key with a different body is rejected.
+#[derive(Clone, Default)]
+pub struct IdempotencyStore {
+    entries: Arc<Mutex<HashMap<(String, String), Entry>>>,
+}
+
+pub enum Begin {
+    /// First use of the key: run the request and `complete` the reservation.
+    New(Reservation),
+    /// Same request already finished.
+    Replay(StoredResponse),
+    /// Same request still running.
+    Wait(watch::Receiver<Option<StoredResponse>>),
+}
+
+/// Claims a key for one request. Dropping it without `complete`, e.g. after
+/// an error, frees the key so the client can try again.
+pub struct Reservation {
+    store: IdempotencyStore,
+    id: (String, String),
+    sender: watch::Sender<Option<StoredResponse>>,
+    completed: bool,
+}
+
+impl Reservation {
+    pub fn complete(mut self, response: StoredResponse) {
+        self.store.with_entries(|entries| {
+            if let Some(entry) = entries.get_mut(&self.id) {
+                entry.slot = Slot::Done(response.clone());
+            }
+        });
+        self.sender.send_replace(Some(response));
+        self.completed = true;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.store.with_entries(|entries| entries.remove(&self.id));
+        }
+    }
+}
+
+impl IdempotencyStore {
+    pub fn begin(&self, user: &str, key: &str, fingerprint: [u8; 32]) -> Result<Begin, AppError> {
+        let id = (user.to_owned(), key.to_owned());
+        self.with_entries(|entries| {
+            entries.retain(|_, entry| entry.created.elapsed() < RETENTION);
+
+            if let Some(entry) = entries.get(&id) {
+                if entry.fingerprint != fingerprint {
+                    return Err(AppError::Unprocessable(
+                        "Idempotency-Key was already used with a different request body"
+                            .to_string(),
+                    ));
+                }
+                return Ok(match &entry.slot {
+                    Slot::Done(response) => Begin::Replay(response.clone()),
+                    Slot::InFlight(receiver) => Begin::Wait(receiver.clone()),
+                });
+            }
+
+            let (sender, receiver) = watch::channel(None);
+            entries.insert(
+                id.clone(),
+                Entry {
+                    fingerprint,
+                    created: Instant::now(),
+                    slot: Slot::InFlight(receiver),
+                },
+            );
+            Ok(Begin::New(Reservation {
+                store: self.clone(),
+                id,
+                sender,
+                completed: false,
+            }))
+        })
+    }
+
+    fn with_entries<T>(&self, f: impl FnOnce(&mut HashMap<(String, String), Entry>) -> T) -> T {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut entries)
+    }
+}
+
+/// Waits for the first request holding the key to finish.
+pub async fn wait(
+    mut receiver: watch::Receiver<Option<StoredResponse>>,
+) -> Result<StoredResponse, AppError> {
+    match receiver.wait_for(Option::is_some).await {
+        Ok(response) => Ok(response.clone().expect("waited for a response")),
+        // The first request failed and released the key.
+        Err(_) => Err(AppError::Conflict(
+            "the original request with this Idempotency-Key failed; retry it".to_string(),
+        )),
+    }
+}
+
+/// The `Idempotency-Key` header, if present. Keys must be 1 to 255 visible
+/// ASCII characters.
+pub fn key(headers: &HeaderMap) -> Result<Option<String>, AppError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .filter(|key| (1..=255).contains(&key.len()))
+        .filter(|key| key.bytes().all(|b| b.is_ascii_graphic()))
+        .map(|key| Some(key.to_owned()))
+        .ok_or_else(|| {
+            AppError::BadRequest(
+                "Idempotency-Key must be 1 to 255 visible ASCII characters".to_string(),
+            )
+        })
+}
+
+/// Hash of a request body in its parsed form, so formatting differences
+/// between retries do not matter.
+pub fn fingerprint(body: &impl Serialize) -> Result<[u8; 32], AppError> {
+    Ok(Sha256::digest(serde_json::to_vec(body)?).into())
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use tracing::{Instrument, info, info_span, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    models::{RemoveItem, RemoveResponse, SessionData},
+    service::{AuditService, ItemOutcome},
+    utils,
+};
+
+/// Finished jobs are kept this long for polling, then forgotten.
+const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for a free slot.
+    Queued,
+    Running,
+    Completed,
+    /// The batch could not run; see `error`.
+    Failed,
+}
+
+/// A removal batch running in the background, as returned by `/jobs/{id}`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Job {
+    pub id: String,
+    #[serde(skip)]
+    pub actor: String,
+    pub status: JobStatus,
+    pub total: usize,
+    pub processed: usize,
+    /// Results so far; the complete `RemoveResponse` once `completed`.
+    pub result: RemoveResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobAccepted {
+    pub job_id: String,
+    /// Where to poll for progress.
+    pub status_url: String,
+}
+
+/// In-memory registry of background removal jobs. At most `max_running`
+/// batches run at once; the rest wait as `queued`. Jobs do not survive a
+/// restart, but a shutdown drains them like any other batch.
+#[derive(Clone)]
+pub struct Jobs {
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+    slots: Arc<Semaphore>,
+}
+
+impl Jobs {
+    pub fn new(max_running: usize) -> Self {
+        Self {
+            jobs: Arc::default(),
+            slots: Arc::new(Semaphore::new(max_running)),
+        }
+    }
+
+    /// Queues `items` for removal as `session` and returns the job ID at once.
+    pub fn submit(
+        &self,
+        service: &AuditService,
+        session: SessionData,
+        items: Vec<RemoveItem>,
+    ) -> String {
+        let id = utils::random_token(16);
+        let now = Utc::now();
+        {
+            let mut jobs = self.lock();
+            jobs.retain(|_, job| {
+                job.finished_at.is_none_or(|at| {
+                    now.signed_duration_since(at).to_std().unwrap_or_default() < RETENTION
+                })
+            });
+            jobs.insert(
+                id.clone(),
+                Job {
+                    id: id.clone(),
+                    actor: session.user_login.clone(),
+                    status: JobStatus::Queued,
+                    total: items.len(),
+                    processed: 0,
+                    result: RemoveResponse {
+                        success: Vec::new(),
+                        failed: Vec::new(),
+                    },
+                    error: None,
+                    created_at: now,
+                    finished_at: None,
+                },
+            );
+        }
+
+        let this = self.clone();
+        let service = service.clone();
+        let job_id = id.clone();
+        // Held while queued too, so a shutdown waits for queued jobs and
+        // reports their items as unprocessed instead of dropping them.
+        let batch = service.begin_batch();
+        let span = info_span!("removal_job", job_id, user = session.user_login);
+        tokio::spawn(
+            async move {
+                let _batch = batch;
+                let Ok(_slot) = this.slots.clone().acquire_owned().await else {
+                    return;
+                };
+                this.update(&job_id, |job| job.status = JobStatus::Running);
+                info!(items = items.len(), "removal job started");
+
+                let outcome = service
+                    .remove_with_progress(&session, items, |outcome| {
+                        this.update(&job_id, |job| {
+                            job.processed += 1;
+                            match outcome {
+                                ItemOutcome::Removed(item) => job.result.success.push(item.clone()),
+                                ItemOutcome::Failed(item) => job.result.failed.push(item.clone()),
+                            }
+                        });
+                    })
+                    .await;
+
+                this.update(&job_id, |job| {
+                    job.finished_at = Some(Utc::now());
+                    match outcome {
+                        Ok(response) => {
+                            job.status = JobStatus::Completed;
+                            job.processed = job.total;
+                            job.result = response;
+                        }
+                        Err(err) => {
+                            warn!(error = %err, "removal job failed");
+                            job.status = JobStatus::Failed;
+                            job.error = Some(err.to_string());
+                        }
+                    }
+                });
+                info!("removal job finished");
+            }
+            .instrument(span),
+        );
+
+        id
+    }
+
+    /// The job with `id`, if it exists and belongs to `actor`.
+    pub fn get(&self, id: &str, actor: &str) -> Option<Job> {
+        self.lock()
+            .get(id)
+            .filter(|job| job.actor == actor)
+            .cloned()
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.lock().get_mut(id) {
+            f(job);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
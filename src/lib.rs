@@ -0,0 +1,336 @@
+pub mod api;
+pub mod audit;
+pub mod auth;
+pub mod config;
+pub mod error;
+pub mod github;
+pub mod github_app;
+pub mod github_fake;
+pub mod handlers;
+pub mod idempotency;
+pub mod jobs;
+pub mod metrics;
+pub mod middleware;
+pub mod models;
+pub mod notify;
+pub mod proposals;
+pub mod redact;
+pub mod service;
+pub mod session;
+pub mod shutdown;
+pub mod teams;
+pub mod utils;
+pub mod workspaces;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Router,
+    middleware::from_fn_with_state,
+    routing::{get, post},
+};
+use axum_extra::extract::cookie::Key;
+use github::{GitHubApi, GitHubClient};
+use github_app::GitHubAppAuth;
+use github_fake::FakeGitHub;
+use sha2::{Digest, Sha512};
+use tokio::{net::TcpListener, time::timeout};
+use tracing::{info, warn};
+
+use crate::{
+    audit::AuditTrail,
+    auth::TokenSessions,
+    config::{AppConfig, SessionStoreConfig},
+    error::AppError,
+    idempotency::IdempotencyStore,
+    jobs::Jobs,
+    models::SessionData,
+    notify::Notifier,
+    proposals::Proposals,
+    service::AuditService,
+    session::{InMemorySessionStore, SessionStore, Sessions, SqliteSessionStore},
+    shutdown::Shutdown,
+    workspaces::Role,
+};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub config: AppConfig,
+    /// Backend for the audit and removal flows.
+    pub github: Arc<dyn GitHubApi>,
+    /// Concrete client for the OAuth exchange, token revocation and GitHub App
+    /// calls, which only make sense against real GitHub.
+    pub github_client: GitHubClient,
+    pub github_app: Option<GitHubAppAuth>,
+    /// Session of the demo fixture's viewer, for demo sign-in. `None` outside
+    /// demo mode; bearer tokens, `GITHUB_TOKEN` included, go through
+    /// `token_sessions`.
+    pub demo_session: Option<SessionData>,
+    pub token_sessions: TokenSessions,
+    pub sessions: Sessions,
+    pub audit: AuditTrail,
+    pub service: AuditService,
+    pub jobs: Jobs,
+    /// Responses to `/remove` kept by `Idempotency-Key`.
+    pub idempotency: IdempotencyStore,
+    /// Removal batches waiting for a second approver.
+    pub proposals: Proposals,
+    /// Lets `main` drain removal batches before exiting.
+    pub shutdown: Shutdown,
+    pub cookie_key: Key,
+}
+
+/// Grace for connections still open once removal batches have drained.
+const CLOSE_GRACE: Duration = Duration::from_secs(5);
+
+impl axum::extract::FromRef<AppState> for Key {
+    fn from_ref(state: &AppState) -> Self {
+        state.cookie_key.clone()
+    }
+}
+
+impl AppState {
+    pub async fn from_config(mut config: AppConfig) -> Result<Self, AppError> {
+        if let Some(path) = config.demo_fixture.clone() {
+            let fake = FakeGitHub::load(&path)?;
+            // `/auth/login` signs visitors in as the fixture's viewer through
+            // the demo session, so no OAuth round trip is needed.
+            config.github_token = Some(fake.accepted_token().to_string());
+            warn!(
+                fixture = %path.display(),
+                "demo mode enabled, serving fixture data and simulating removals"
+            );
+            return Self::with_github(config, Arc::new(fake)).await;
+        }
+
+        let github = GitHubClient::new(&config.github_api_url)?.with_retry(config.retry);
+        Self::with_github(config, Arc::new(github)).await
+    }
+
+    /// Builds the state around any `GitHubApi` backend, such as a fake.
+    pub async fn with_github(
+        config: AppConfig,
+        github: Arc<dyn GitHubApi>,
+    ) -> Result<Self, AppError> {
+        for secret in [
+            Some(config.github_client_secret.as_str()),
+            Some(config.session_secret.as_str()),
+            config.github_token.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(config.notify_webhooks.iter().map(url::Url::as_str))
+        .chain(config.github_app.iter().flat_map(|app| {
+            // Each base64 line of the PEM too, in case the key is logged in part.
+            std::iter::once(app.private_key_pem.as_str()).chain(
+                app.private_key_pem
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.starts_with("-----")),
+            )
+        })) {
+            redact::register_secret(secret);
+        }
+
+        let cookie_key = {
+            let mut hasher = Sha512::new();
+            hasher.update(config.session_secret.as_bytes());
+            let derived = hasher.finalize();
+            Key::from(derived.as_slice())
+        };
+        let github_client = GitHubClient::new(&config.github_api_url)?.with_retry(config.retry);
+        let github_app = config
+            .github_app
+            .as_ref()
+            .map(GitHubAppAuth::new)
+            .transpose()?;
+        if github_app.is_some() {
+            info!("github app mode enabled");
+        }
+
+        let session_store: Arc<dyn SessionStore> = match &config.session_store {
+            SessionStoreConfig::Memory => Arc::new(InMemorySessionStore::default()),
+            SessionStoreConfig::Sqlite(path) => {
+                Arc::new(SqliteSessionStore::open(path, &cookie_key)?)
+            }
+        };
+        let sessions = Sessions::new(
+            session_store,
+            config.session_idle_timeout,
+            config.session_absolute_timeout,
+        );
+        sessions.spawn_purge_task(Duration::from_secs(10 * 60));
+
+        let audit = AuditTrail::open(config.audit_log_path.as_deref())?;
+        let shutdown = Shutdown::new(config.drain_timeout);
+        let service = AuditService::new(github.clone(), audit.clone())
+            .with_policy(config.removal_policy.clone())
+            .with_notifier(Notifier::new(config.notify_webhooks.clone()))
+            .with_shutdown(shutdown.clone())
+            .with_concurrency(config.max_concurrency)
+            .with_mutation_interval(config.mutation_interval);
+
+        let jobs = Jobs::new(config.max_running_jobs);
+
+        let demo_session = match (&config.demo_fixture, config.github_token.as_deref()) {
+            (Some(_), Some(token)) => Some(auth::session_from_token(github.as_ref(), token).await?),
+            _ => None,
+        };
+
+        Ok(Self {
+            config,
+            github,
+            github_client,
+            github_app,
+            demo_session,
+            token_sessions: TokenSessions::default(),
+            sessions,
+            audit,
+            service,
+            jobs,
+            idempotency: IdempotencyStore::default(),
+            proposals: Proposals::default(),
+            shutdown,
+            cookie_key,
+        })
+    }
+}
+
+/// Routes under `/workspaces/{workspace}`, each behind the least role it needs.
+fn workspace_routes(state: &AppState) -> Router<AppState> {
+    let role = |role: Role| from_fn_with_state((state.clone(), role), middleware::require_role);
+
+    let viewer = Router::new()
+        .route(
+            "/workspaces/{workspace}/audit",
+            get(handlers::workspace_audit),
+        )
+        .route(
+            "/workspaces/{workspace}/export",
+            get(handlers::workspace_export),
+        )
+        .route(
+            "/workspaces/{workspace}/proposals",
+            get(handlers::workspace_proposals),
+        )
+        .route(
+            "/workspaces/{workspace}/outside-collaborators",
+            get(handlers::workspace_outside_collaborators),
+        )
+        .route(
+            "/workspaces/{workspace}/teams",
+            get(handlers::workspace_teams),
+        )
+        .route(
+            "/workspaces/{workspace}/teams/view",
+            get(handlers::workspace_teams_view),
+        )
+        .route_layer(role(Role::Viewer));
+    let remover = Router::new()
+        .route(
+            "/workspaces/{workspace}/remove",
+            post(handlers::workspace_remove),
+        )
+        .route(
+            "/workspaces/{workspace}/proposals",
+            post(handlers::workspace_submit_proposal),
+        )
+        .route(
+            "/workspaces/{workspace}/outside-collaborators/remove",
+            post(handlers::workspace_remove_outside_collaborators),
+        )
+        .route(
+            "/workspaces/{workspace}/teams/{team}/repos/{repo}/remove",
+            post(handlers::workspace_remove_team_repo),
+        )
+        .route(
+            "/workspaces/{workspace}/teams/{team}/members/{username}/remove",
+            post(handlers::workspace_remove_team_member),
+        )
+        .route_layer(role(Role::Remover));
+    let approver = Router::new()
+        .route(
+            "/workspaces/{workspace}/proposals/{id}/approve",
+            post(handlers::workspace_approve_proposal),
+        )
+        .route(
+            "/workspaces/{workspace}/proposals/{id}/reject",
+            post(handlers::workspace_reject_proposal),
+        )
+        .route_layer(role(Role::Approver));
+    let admin = Router::new()
+        .route(
+            "/workspaces/{workspace}/members",
+            get(handlers::workspace_members),
+        )
+        .route_layer(role(Role::Admin));
+
+    viewer.merge(remover).merge(approver).merge(admin)
+}
+
+pub fn app(state: AppState) -> Router {
+    let protected = Router::new()
+        .route("/dashboard", get(handlers::dashboard))
+        .route("/app/dashboard", get(handlers::app_dashboard))
+        .route("/diagnostics", get(handlers::diagnostics))
+        .route("/logout", post(handlers::logout))
+        .route("/logout/all", post(handlers::logout_all))
+        .route("/remove", post(handlers::remove_collaborators))
+        .route("/jobs/{id}", get(handlers::job_status))
+        .route("/deploy-keys", get(handlers::deploy_keys))
+        .route("/deploy-keys/remove", post(handlers::remove_deploy_keys))
+        .route("/workspaces", get(handlers::list_workspaces))
+        .merge(workspace_routes(&state))
+        .layer(from_fn_with_state(state.clone(), middleware::csrf_protect))
+        .layer(from_fn_with_state(state.clone(), middleware::require_auth));
+
+    let mut public = Router::new()
+        .route("/", get(handlers::index))
+        .route("/health", get(handlers::health));
+    if state.config.metrics_enabled {
+        public = public.route("/metrics", get(handlers::metrics));
+    }
+
+    public
+        .route("/auth/login", get(handlers::auth_login))
+        .route("/auth/callback", get(handlers::auth_callback))
+        .merge(protected)
+        .merge(api::router(&state))
+        .layer(axum::middleware::from_fn(middleware::trace_request))
+        .layer(axum::middleware::from_fn(middleware::request_id))
+        .with_state(state)
+}
+
+/// Serves `state` on `listener` until `state.shutdown` is requested. Stops
+/// accepting connections at once, then always waits for in-flight removal
+/// batches to drain before returning; connections still open after that get
+/// `CLOSE_GRACE` to close.
+pub async fn serve(listener: TcpListener, state: AppState) -> std::io::Result<()> {
+    let drain = state.shutdown.clone();
+    let requested = drain.clone();
+    let mut server = tokio::spawn(
+        axum::serve(listener, app(state))
+            .with_graceful_shutdown(async move { requested.requested().await })
+            .into_future(),
+    );
+
+    let served = tokio::select! {
+        result = &mut server => Some(result),
+        () = drain.requested() => None,
+    };
+    // The server can also stop on an accept error; batches still drain then.
+    drain.request();
+    drain.drain().await;
+
+    let result = match served {
+        Some(result) => result,
+        None => match timeout(CLOSE_GRACE, server).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("closing connections still open after removal batches drained");
+                return Ok(());
+            }
+        },
+    };
+    result.map_err(std::io::Error::other)?
+}
@@ -1,76 +1,26 @@
-mod auth;
-mod error;
-mod github;
-mod handlers;
-mod middleware;
-mod models;
-mod utils;
-
-use std::net::SocketAddr;
-
-use axum::{
-    Router,
-    middleware::from_fn_with_state,
-    routing::{get, post},
-};
-use axum_extra::extract::cookie::Key;
-use github::GitHubClient;
-use sha2::{Digest, Sha512};
+use learning_rust::{AppState, config::AppConfig, serve, shutdown, utils};
 use tokio::net::TcpListener;
 use tracing::info;
 
-use crate::utils::AppConfig;
-
-#[derive(Clone)]
-pub struct AppState {
-    pub config: AppConfig,
-    pub github: GitHubClient,
-    pub cookie_key: Key,
-}
-
-impl axum::extract::FromRef<AppState> for Key {
-    fn from_ref(state: &AppState) -> Self {
-        state.cookie_key.clone()
-    }
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    utils::init_tracing();
-
-    let config = AppConfig::from_env()?;
-    let cookie_key = {
-        let mut hasher = Sha512::new();
-        hasher.update(config.session_secret.as_bytes());
-        let derived = hasher.finalize();
-        Key::from(derived.as_slice())
-    };
-    let github = GitHubClient::new()?;
-
-    let state = AppState {
-        config,
-        github,
-        cookie_key,
-    };
+    let _tracing = utils::init_tracing();
 
-    let protected = Router::new()
-        .route("/dashboard", get(handlers::dashboard))
-        .route("/logout", post(handlers::logout))
-        .route("/remove", post(handlers::remove_collaborators))
-        .layer(from_fn_with_state(state.clone(), middleware::csrf_protect))
-        .layer(from_fn_with_state(state.clone(), middleware::require_auth));
+    let config = AppConfig::load()?;
+    let state = AppState::from_config(config).await?;
 
-    let app = Router::new()
-        .route("/", get(handlers::index))
-        .route("/health", get(handlers::health))
-        .route("/auth/login", get(handlers::auth_login))
-        .route("/auth/callback", get(handlers::auth_callback))
-        .merge(protected)
-        .with_state(state);
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
+    let addr = state.config.bind_addr;
     let listener = TcpListener::bind(addr).await?;
     info!("listening on {addr}");
-    axum::serve(listener, app).await?;
+
+    let signalled = state.shutdown.clone();
+    tokio::spawn(async move {
+        shutdown::signal().await;
+        info!("shutdown signal received, no longer accepting connections");
+        signalled.request();
+    });
+
+    serve(listener, state).await?;
+    info!("shutdown complete");
     Ok(())
 }
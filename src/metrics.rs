@@ -0,0 +1,123 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+/// Process-wide Prometheus metrics, served at `/metrics`. They live in a
+/// static rather than `AppState` because `GitHubClient` is also used outside
+/// the server, e.g. by `audit-cli`, where recording is simply never scraped.
+pub struct Metrics {
+    registry: Registry,
+    /// GitHub REST calls by endpoint template, method and status. Transport
+    /// failures are counted with status `error`.
+    pub github_requests: IntCounterVec,
+    /// Latency of the same calls, with the same labels.
+    pub github_request_duration: HistogramVec,
+    /// Sleeps in `send_with_retry` after a rate-limited response.
+    pub github_backoffs: IntCounterVec,
+    /// Time to build a dashboard page, including the GitHub calls behind it.
+    pub dashboard_render_duration: HistogramVec,
+    /// Repositories examined by an audit, by whether collaborators were listed.
+    pub repos_audited: IntCounterVec,
+    /// Collaborator removals by outcome and a fixed reason label, see
+    /// `models::FailureKind`.
+    pub removals: IntCounterVec,
+    /// Deploy key deletions, labelled like `removals`.
+    pub deploy_key_removals: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let github_requests = IntCounterVec::new(
+            Opts::new("github_requests_total", "GitHub API requests"),
+            &["endpoint", "method", "status"],
+        )
+        .expect("valid metric");
+        let github_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "github_request_duration_seconds",
+                "GitHub API request latency",
+            ),
+            &["endpoint", "method", "status"],
+        )
+        .expect("valid metric");
+        let github_backoffs = IntCounterVec::new(
+            Opts::new(
+                "github_rate_limit_backoffs_total",
+                "Rate-limit backoffs before retrying a GitHub request",
+            ),
+            &["endpoint"],
+        )
+        .expect("valid metric");
+        let dashboard_render_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "dashboard_render_duration_seconds",
+                "Time to build a dashboard page",
+            )
+            .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["view"],
+        )
+        .expect("valid metric");
+        let repos_audited = IntCounterVec::new(
+            Opts::new("repos_audited_total", "Repositories examined by audits"),
+            &["outcome"],
+        )
+        .expect("valid metric");
+        let removals = IntCounterVec::new(
+            Opts::new(
+                "collaborator_removals_total",
+                "Collaborator removal attempts",
+            ),
+            &["outcome", "reason"],
+        )
+        .expect("valid metric");
+        let deploy_key_removals = IntCounterVec::new(
+            Opts::new("deploy_key_removals_total", "Deploy key deletion attempts"),
+            &["outcome", "reason"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(github_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(github_request_duration.clone()),
+            Box::new(github_backoffs.clone()),
+            Box::new(dashboard_render_duration.clone()),
+            Box::new(repos_audited.clone()),
+            Box::new(removals.clone()),
+            Box::new(deploy_key_removals.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+
+        Self {
+            registry,
+            github_requests,
+            github_request_duration,
+            github_backoffs,
+            dashboard_render_duration,
+            repos_audited,
+            removals,
+            deploy_key_removals,
+        }
+    }
+
+    /// Current values in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::warn!(error = %err, "failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
@@ -1,12 +1,66 @@
 use axum::{
-    extract::State,
-    http::{Method, Request, StatusCode},
+    extract::{OriginalUri, Path, State},
+    http::{HeaderValue, Method, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Redirect, Response},
 };
 use axum_extra::extract::PrivateCookieJar;
+use tracing::{Instrument, field, info_span, warn};
 
-use crate::{AppState, auth, models::SessionData};
+use crate::{
+    AppState, auth,
+    error::AppError,
+    models::{AuthMethod, RequestId, SessionData},
+    utils,
+    workspaces::{Role, WorkspaceAccess},
+};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Assigns each request an ID and echoes it in `X-Request-Id`. A caller's own
+/// ID is kept when it is short and plain enough to log safely.
+pub async fn request_id(mut request: Request<axum::body::Body>, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| {
+            (1..=64).contains(&id.len())
+                && id
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+        })
+        .map(str::to_owned)
+        .unwrap_or_else(|| utils::random_token(16));
+
+    request.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Opens the root span of each request. Handler spans, GitHub calls and
+/// per-repository audit work nest under it, so every log line they emit
+/// carries the request ID, and error bodies report its trace.
+pub async fn trace_request(request: Request<axum::body::Body>, next: Next) -> Response {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_default();
+    let span = info_span!(
+        "request",
+        request_id,
+        http.request.method = %request.method(),
+        url.path = request.uri().path(),
+        http.response.status_code = field::Empty,
+    );
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("http.response.status_code", response.status().as_u16());
+    response
+}
 
 pub async fn require_auth(
     State(state): State<AppState>,
@@ -14,10 +68,30 @@ pub async fn require_auth(
     request: Request<axum::body::Body>,
     next: Next,
 ) -> Response {
-    let path = request.uri().path().to_owned();
+    // Nested routers see a stripped URI; the original one tells API routes apart.
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().path(), |uri| uri.path())
+        .to_owned();
+
+    if let Some(token) = auth::bearer_token(request.headers()).map(str::to_owned) {
+        let session = state
+            .token_sessions
+            .session(state.github.as_ref(), &token)
+            .await;
+        return match session {
+            Ok(session) => {
+                run_authenticated(&state, next, request, session, AuthMethod::Token).await
+            }
+            Err(err) => err.into_response(),
+        };
+    }
 
-    match auth::read_session(&jar) {
-        Ok(Some(session)) => run_authenticated(next, request, session).await,
+    match auth::read_session(&jar, &state.sessions) {
+        Ok(Some(session)) => {
+            run_authenticated(&state, next, request, session, AuthMethod::Session).await
+        }
         _ => unauthenticated_response(
             path.as_str(),
             jar,
@@ -26,14 +100,11 @@ pub async fn require_auth(
     }
 }
 
-pub async fn csrf_protect(
-    jar: PrivateCookieJar,
-    request: Request<axum::body::Body>,
-    next: Next,
-) -> Response {
+pub async fn csrf_protect(request: Request<axum::body::Body>, next: Next) -> Response {
     let method = request.method().clone();
 
-    if !requires_csrf(&method) {
+    let token_auth = request.extensions().get::<AuthMethod>() == Some(&AuthMethod::Token);
+    if !requires_csrf(&method) || token_auth {
         return next.run(request).await;
     }
 
@@ -43,9 +114,8 @@ pub async fn csrf_protect(
         .and_then(|v| v.to_str().ok())
         .map(str::trim);
 
-    let session = match auth::read_session(&jar) {
-        Ok(Some(session)) => session,
-        _ => return (StatusCode::UNAUTHORIZED, "authentication required").into_response(),
+    let Some(session) = request.extensions().get::<SessionData>() else {
+        return (StatusCode::UNAUTHORIZED, "authentication required").into_response();
     };
 
     match header_token {
@@ -54,18 +124,74 @@ pub async fn csrf_protect(
     }
 }
 
+#[derive(serde::Deserialize)]
+pub struct WorkspaceParams {
+    workspace: String,
+}
+
+/// Lets the request through only if the session user holds at least `role`
+/// in the workspace named by the `{workspace}` path segment, and hands the
+/// handler a `WorkspaceAccess`. Runs as a route layer inside `require_auth`.
+pub async fn require_role(
+    State((state, role)): State<(AppState, Role)>,
+    Path(params): Path<WorkspaceParams>,
+    mut request: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let Some(session) = request.extensions().get::<SessionData>() else {
+        return AppError::Auth.into_response();
+    };
+    let Some(workspace) = state.config.workspace(&params.workspace) else {
+        return AppError::NotFound(format!("workspace not found: {}", params.workspace))
+            .into_response();
+    };
+
+    match workspace.role_of(&session.user_login) {
+        Some(held) if held >= role => {
+            let access = WorkspaceAccess {
+                workspace: workspace.clone(),
+                role: held,
+            };
+            request.extensions_mut().insert(access);
+            next.run(request).await
+        }
+        held => {
+            warn!(
+                user = session.user_login,
+                workspace = workspace.name,
+                held = ?held,
+                required = ?role,
+                "workspace role too low"
+            );
+            AppError::Forbidden.into_response()
+        }
+    }
+}
+
 async fn run_authenticated(
+    state: &AppState,
     next: Next,
     mut request: Request<axum::body::Body>,
     session: SessionData,
+    method: AuthMethod,
 ) -> Response {
+    // Also covers sessions and tokens issued before the allowlist changed.
+    if !state.config.is_user_allowed(&session.user_login) {
+        warn!(user = session.user_login, "user is not on the allowlist");
+        return AppError::Forbidden.into_response();
+    }
     request.extensions_mut().insert(session);
+    request.extensions_mut().insert(method);
     next.run(request).await
 }
 
 fn unauthenticated_response(path: &str, jar: PrivateCookieJar, secure: bool) -> Response {
     let cleared = auth::clear_session(jar, secure);
 
+    if path.starts_with("/api/") {
+        return (cleared, AppError::Auth).into_response();
+    }
+
     if path.starts_with("/remove") {
         return (
             cleared,
@@ -1,18 +1,23 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionData {
     pub access_token: String,
     pub user_login: String,
     pub csrf_token: String,
+    /// Scopes GitHub reported when the token was obtained, if known.
+    #[serde(default)]
+    pub granted_scopes: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Owner {
     pub login: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Repository {
     pub id: u64,
     pub name: String,
@@ -22,7 +27,7 @@ pub struct Repository {
     pub fork: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct Permissions {
     #[serde(default)]
     pub admin: bool,
@@ -36,7 +41,7 @@ pub struct Permissions {
     pub triage: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Collaborator {
     pub login: String,
     pub id: u64,
@@ -45,15 +50,16 @@ pub struct Collaborator {
     pub role_name: Option<String>,
 }
 
-impl Collaborator {
-    pub fn permission_label(&self) -> &'static str {
-        if self.permissions.admin {
+impl Permissions {
+    /// The highest permission held, as GitHub names it in the UI.
+    pub fn label(&self) -> &'static str {
+        if self.admin {
             "admin"
-        } else if self.permissions.maintain {
+        } else if self.maintain {
             "maintain"
-        } else if self.permissions.push {
+        } else if self.push {
             "write"
-        } else if self.permissions.triage {
+        } else if self.triage {
             "triage"
         } else {
             "read"
@@ -61,13 +67,25 @@ impl Collaborator {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Collaborator {
+    pub fn permission_label(&self) -> &'static str {
+        self.permissions.label()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RepoWithCollaborators {
     pub repo: Repository,
     pub collaborators: Vec<Collaborator>,
     pub can_remove: bool,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct RepoAudit {
+    pub rows: Vec<RepoWithCollaborators>,
+    pub forbidden: Vec<Repository>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct OAuthCallbackQuery {
     pub code: Option<String>,
@@ -77,35 +95,155 @@ pub struct OAuthCallbackQuery {
 }
 
 #[derive(Debug, Deserialize)]
+pub struct LandingQuery {
+    pub revocation: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct RemoveRequest {
     pub items: Vec<RemoveItem>,
+    /// Queue the batch as a job and answer `202` with its ID instead of
+    /// waiting for the result.
+    #[serde(default)]
+    pub background: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// An organization outside collaborator and the organization repositories
+/// they are a collaborator on.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OutsideCollaborator {
+    pub login: String,
+    pub repos: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OrgRemoveRequest {
+    pub usernames: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProposalRequest {
+    pub items: Vec<RemoveItem>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct RemoveItem {
     pub repo: String,
     pub username: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct RemoveSuccess {
     pub repo: String,
     pub username: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct RemoveFailure {
     pub repo: String,
     pub username: String,
     pub reason: String,
+    /// Metric label for `reason`; not part of the response.
+    #[serde(skip)]
+    pub kind: FailureKind,
+}
+
+/// Why a removal or deploy key deletion failed, as the fixed set of values
+/// used for the `reason` metric label. The message next to it can carry
+/// upstream detail such as a status line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Blank, or a name GitHub would not accept.
+    Invalid,
+    /// Not attempted before the drain deadline.
+    Shutdown,
+    SelfRemoval,
+    Protected,
+    ApprovalRequired,
+    NotOwner,
+    NotAdmin,
+    Forbidden,
+    Unprocessable,
+    NotFound,
+    OrgMember,
+    /// The request to GitHub failed.
+    Upstream,
+    UnexpectedStatus,
+}
+
+impl FailureKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Invalid => "invalid",
+            Self::Shutdown => "shutdown",
+            Self::SelfRemoval => "self",
+            Self::Protected => "protected",
+            Self::ApprovalRequired => "approval_required",
+            Self::NotOwner => "not_owner",
+            Self::NotAdmin => "not_admin",
+            Self::Forbidden => "forbidden",
+            Self::Unprocessable => "unprocessable",
+            Self::NotFound => "not_found",
+            Self::OrgMember => "org_member",
+            Self::Upstream => "upstream",
+            Self::UnexpectedStatus => "unexpected_status",
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct RemoveResponse {
     pub success: Vec<RemoveSuccess>,
     pub failed: Vec<RemoveFailure>,
 }
 
+/// An SSH key with access to a single repository.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct DeployKey {
+    pub id: u64,
+    pub title: String,
+    /// `false` when the key can push.
+    pub read_only: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Unset for keys never used, and on GitHub versions that do not track it.
+    #[serde(default)]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RepoDeployKeys {
+    pub repo: Repository,
+    /// `None` when GitHub refused to list the keys, as it does without admin.
+    pub keys: Option<Vec<DeployKey>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeployKeyRemoveRequest {
+    pub items: Vec<DeployKeyItem>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct DeployKeyItem {
+    pub repo: String,
+    pub key_id: u64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeployKeyFailure {
+    pub repo: String,
+    pub key_id: u64,
+    pub reason: String,
+    /// Metric label for `reason`; not part of the response.
+    #[serde(skip)]
+    pub kind: FailureKind,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeployKeyRemoveResponse {
+    pub success: Vec<DeployKeyItem>,
+    pub failed: Vec<DeployKeyFailure>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GitHubAccessTokenResponse {
     pub access_token: Option<String>,
@@ -115,19 +253,65 @@ pub struct GitHubAccessTokenResponse {
     pub error_description: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct GitHubUser {
     pub login: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct TokenIdentity {
+    pub user: GitHubUser,
+    /// Value of the `x-oauth-scopes` header; absent for fine-grained tokens.
+    pub scopes: Option<String>,
+}
+
+/// Correlation ID of the current request, taken from `X-Request-Id` when the
+/// caller supplies a well-formed one and generated otherwise.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// How the current request was authenticated. Token-authenticated requests
+/// carry no browser cookies, so they are exempt from CSRF checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    Session,
+    Token,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct CollaboratorPermission {
     pub permission: String,
     pub role_name: Option<String>,
     pub user: GitHubUser,
 }
 
+#[derive(Debug, Clone)]
+pub enum SsoStatus {
+    Authorized,
+    Required { authorize_url: Option<String> },
+    Unavailable(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RateLimitResponse {
+    pub resources: RateLimitResources,
+}
+
 #[derive(Debug, Deserialize)]
+pub struct RateLimitResources {
+    pub core: RateLimitBucket,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitBucket {
+    pub limit: u64,
+    pub remaining: u64,
+    pub used: u64,
+    pub reset: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct DashboardQuery {
     #[serde(default)]
     pub ignore_forks: bool,
@@ -149,3 +333,20 @@ impl From<DashboardQuery> for RepoFilterOptions {
         }
     }
 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Installation {
+    pub id: u64,
+    pub account: Owner,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallationAccessToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallationRepositories {
+    pub repositories: Vec<Repository>,
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use reqwest::Client;
+use serde_json::{Value, json};
+use tracing::warn;
+use url::Url;
+
+use crate::models::{DeployKeyRemoveResponse, RemoveResponse};
+
+/// Posts a JSON summary of every removal batch to the configured webhooks,
+/// e.g. a Slack or Teams incoming webhook relay. Delivery is best effort and
+/// never delays or fails the batch itself.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    http: Client,
+    webhooks: Arc<Vec<Url>>,
+}
+
+impl Notifier {
+    pub fn new(webhooks: Vec<Url>) -> Self {
+        Self {
+            http: Client::new(),
+            webhooks: Arc::new(webhooks),
+        }
+    }
+
+    pub fn collaborators_removed(&self, actor: &str, response: &RemoveResponse) {
+        self.send(json!({
+            "event": "collaborator_remove",
+            "actor": actor,
+            "success": response.success,
+            "failed": response.failed,
+        }));
+    }
+
+    pub fn deploy_keys_removed(&self, actor: &str, response: &DeployKeyRemoveResponse) {
+        self.send(json!({
+            "event": "deploy_key_remove",
+            "actor": actor,
+            "success": response.success,
+            "failed": response.failed,
+        }));
+    }
+
+    fn send(&self, payload: Value) {
+        for webhook in self.webhooks.iter().cloned() {
+            let http = self.http.clone();
+            let payload = payload.clone();
+            tokio::spawn(async move {
+                // Webhook URLs embed their credentials, so only the host is logged.
+                let host = webhook.host_str().unwrap_or_default().to_string();
+                match http.post(webhook).json(&payload).send().await {
+                    Ok(response) if !response.status().is_success() => {
+                        warn!(host, status = %response.status(), "webhook rejected notification");
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        warn!(host, error = %err.without_url(), "webhook notification failed");
+                    }
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    error::AppError,
+    models::{RemoveItem, RemoveResponse, SessionData},
+    utils,
+    workspaces::Workspace,
+};
+
+/// Proposals are forgotten this long after being submitted, decided or not.
+const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    /// Waiting for a second admin.
+    Pending,
+    /// Approved and run; see `result`.
+    Approved,
+    Rejected,
+}
+
+/// A removal batch held until a second admin approves or rejects it. Not
+/// `Debug`, since it holds the proposer's access token.
+#[derive(Clone, Serialize, ToSchema)]
+pub struct Proposal {
+    pub id: String,
+    pub proposer: String,
+    /// Organization owning the repositories in `items`.
+    pub owner: String,
+    /// Workspace the proposal was submitted in, and the only one it can be
+    /// decided through.
+    pub workspace: String,
+    pub status: ProposalStatus,
+    pub items: Vec<RemoveItem>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decided_at: Option<DateTime<Utc>>,
+    /// Outcome of the removal once an approved proposal has run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<RemoveResponse>,
+    /// The proposer's session; the batch runs with their token.
+    #[serde(skip)]
+    pub session: SessionData,
+    /// ID of the browser session the proposal came from, `None` for bearer
+    /// tokens. Approval requires that session to still be live.
+    #[serde(skip)]
+    pub session_id: Option<String>,
+}
+
+/// In-memory store of removal proposals. Like jobs, proposals do not survive
+/// a restart.
+#[derive(Clone, Default)]
+pub struct Proposals {
+    proposals: Arc<Mutex<HashMap<String, Proposal>>>,
+}
+
+impl Proposals {
+    /// Stores a pending proposal for `items` on the organization of
+    /// `workspace`.
+    pub fn submit(
+        &self,
+        session: SessionData,
+        session_id: Option<String>,
+        workspace: &Workspace,
+        items: Vec<RemoveItem>,
+    ) -> Proposal {
+        let now = Utc::now();
+        let proposal = Proposal {
+            id: utils::random_token(16),
+            proposer: session.user_login.clone(),
+            owner: workspace.org.clone(),
+            workspace: workspace.name.clone(),
+            status: ProposalStatus::Pending,
+            items,
+            created_at: now,
+            decided_by: None,
+            decided_at: None,
+            result: None,
+            session,
+            session_id,
+        };
+
+        let mut proposals = self.lock();
+        proposals.retain(|_, p| {
+            now.signed_duration_since(p.created_at)
+                .to_std()
+                .unwrap_or_default()
+                < RETENTION
+        });
+        proposals.insert(proposal.id.clone(), proposal.clone());
+        proposal
+    }
+
+    /// Pending proposals in `workspace`, oldest first.
+    pub fn pending(&self, workspace: &str) -> Vec<Proposal> {
+        let mut pending: Vec<_> = self
+            .lock()
+            .values()
+            .filter(|p| p.status == ProposalStatus::Pending)
+            .filter(|p| p.workspace == workspace)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|p| p.created_at);
+        pending
+    }
+
+    /// The proposal with `id`, if it belongs to `workspace`.
+    pub fn get(&self, id: &str, workspace: &str) -> Option<Proposal> {
+        self.lock()
+            .get(id)
+            .filter(|p| p.workspace == workspace)
+            .cloned()
+    }
+
+    /// Moves a pending proposal to `status`, so it can be decided only once.
+    pub fn decide(
+        &self,
+        id: &str,
+        approver: &str,
+        status: ProposalStatus,
+    ) -> Result<Proposal, AppError> {
+        let mut proposals = self.lock();
+        let proposal = proposals
+            .get_mut(id)
+            .ok_or_else(|| AppError::NotFound(format!("proposal not found: {id}")))?;
+        if proposal.status != ProposalStatus::Pending {
+            return Err(AppError::Conflict(format!(
+                "proposal {id} was already decided by {}",
+                proposal.decided_by.as_deref().unwrap_or_default()
+            )));
+        }
+        proposal.status = status;
+        proposal.decided_by = Some(approver.to_owned());
+        proposal.decided_at = Some(Utc::now());
+        Ok(proposal.clone())
+    }
+
+    pub fn complete(&self, id: &str, result: RemoveResponse) -> Option<Proposal> {
+        let mut proposals = self.lock();
+        let proposal = proposals.get_mut(id)?;
+        proposal.result = Some(result);
+        Some(proposal.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Proposal>> {
+        self.proposals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
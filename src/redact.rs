@@ -0,0 +1,191 @@
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    sync::RwLock,
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Prefixes of GitHub-issued tokens: classic and fine-grained PATs, OAuth,
+/// user-to-server, installation and refresh tokens.
+const TOKEN_PREFIXES: [&str; 6] = ["github_pat_", "ghp_", "gho_", "ghu_", "ghs_", "ghr_"];
+
+/// Keys whose values are secret wherever they appear as `key=value`,
+/// `key: value` or `"key":"value"`. The generic `code` and `token` also occur
+/// in plain prose, as in `status code: 404`, so for those only `key=value`
+/// and `"key":` count.
+const SECRET_KEYS: [&str; 7] = [
+    "access_token",
+    "refresh_token",
+    "client_secret",
+    "csrf_token",
+    "code",
+    "token",
+    "password",
+];
+
+/// Keys from `SECRET_KEYS` that are redacted after `:` only when quoted.
+const LOOSE_KEYS: [&str; 2] = ["code", "token"];
+
+const AUTH_SCHEMES: [&str; 3] = ["Bearer ", "bearer ", "Basic "];
+
+/// Secrets only known at runtime, such as the configured client secret.
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Redacts `secret` verbatim from every later log line. Values shorter than
+/// eight characters are ignored; redacting them would mangle ordinary text.
+pub fn register_secret(secret: &str) {
+    if secret.len() < 8 {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap_or_else(|e| e.into_inner());
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_owned());
+    }
+}
+
+/// Replaces tokens, OAuth codes, client secrets and registered secrets in
+/// `input` with `[REDACTED]`.
+pub fn redact(input: &str) -> Cow<'_, str> {
+    let secrets = SECRETS.read().unwrap_or_else(|e| e.into_inner());
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut changed = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+
+        if let Some(secret) = secrets.iter().find(|s| rest.starts_with(s.as_bytes())) {
+            out.extend_from_slice(REDACTED.as_bytes());
+            i += secret.len();
+            changed = true;
+            continue;
+        }
+
+        if at_word_start(bytes, i) {
+            if let Some(prefix) = TOKEN_PREFIXES
+                .iter()
+                .find(|p| rest.starts_with(p.as_bytes()))
+            {
+                let len = prefix.len() + run_len(&rest[prefix.len()..], is_token_byte);
+                out.extend_from_slice(REDACTED.as_bytes());
+                i += len;
+                changed = true;
+                continue;
+            }
+
+            if let Some(scheme) = AUTH_SCHEMES.iter().find(|s| rest.starts_with(s.as_bytes())) {
+                let value = run_len(&rest[scheme.len()..], is_value_byte);
+                if value > 0 {
+                    out.extend_from_slice(scheme.as_bytes());
+                    out.extend_from_slice(REDACTED.as_bytes());
+                    i += scheme.len() + value;
+                    changed = true;
+                    continue;
+                }
+            }
+
+            if let Some(prefix_len) = secret_key_prefix(rest) {
+                let value = run_len(&rest[prefix_len..], is_value_byte);
+                if value > 0 {
+                    out.extend_from_slice(&rest[..prefix_len]);
+                    out.extend_from_slice(REDACTED.as_bytes());
+                    i += prefix_len + value;
+                    changed = true;
+                    continue;
+                }
+            }
+        }
+
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    if !changed {
+        return Cow::Borrowed(input);
+    }
+    // Only ASCII-delimited runs were replaced, so `out` is still valid UTF-8.
+    Cow::Owned(
+        String::from_utf8(out)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()),
+    )
+}
+
+/// Length of a secret key plus its separator at the start of `rest`, e.g.
+/// `code=`, `client_secret: "` or `"access_token":"`.
+fn secret_key_prefix(rest: &[u8]) -> Option<usize> {
+    let quoted = rest.first() == Some(&b'"');
+    let start = usize::from(quoted);
+    let key = SECRET_KEYS
+        .iter()
+        .find(|k| rest[start..].starts_with(k.as_bytes()))?;
+
+    let loose = !quoted && LOOSE_KEYS.contains(key);
+
+    let mut pos = start + key.len();
+    if quoted {
+        if rest.get(pos) != Some(&b'"') {
+            return None;
+        }
+        pos += 1;
+    }
+    match rest.get(pos) {
+        Some(b'=') => pos += 1,
+        Some(b':') if !loose => pos += 1,
+        _ => return None,
+    }
+    pos += run_len(&rest[pos..], |b| b == b' ');
+    if rest[pos..].starts_with(b"\\\"") {
+        pos += 2;
+    } else if rest.get(pos) == Some(&b'"') {
+        pos += 1;
+    }
+    Some(pos)
+}
+
+fn at_word_start(bytes: &[u8], i: usize) -> bool {
+    i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || matches!(bytes[i - 1], b'_' | b'.'))
+}
+
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+fn is_value_byte(b: u8) -> bool {
+    !(b.is_ascii_whitespace() || matches!(b, b'"' | b'\\' | b'&' | b',' | b';' | b'}' | b')'))
+}
+
+fn run_len(bytes: &[u8], pred: impl Fn(u8) -> bool) -> usize {
+    bytes.iter().take_while(|&&b| pred(b)).count()
+}
+
+/// `MakeWriter` that passes every formatted log line through [`redact`]
+/// before it reaches the wrapped writer.
+pub struct Redacting<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+pub struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    // The fmt layers write each event in one call, so a secret is never split
+    // across two writes.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,965 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{StreamExt, stream};
+use reqwest::StatusCode;
+use tokio::{
+    sync::Mutex as AsyncMutex,
+    time::{Instant, sleep_until},
+};
+use tracing::{Instrument, error, info, info_span, warn};
+
+use crate::{
+    audit::{AuditEvent, AuditOutcome, AuditTrail},
+    error::AppError,
+    github::{GitHubApi, GitHubClient},
+    metrics,
+    models::{
+        DeployKeyFailure, DeployKeyItem, DeployKeyRemoveResponse, FailureKind, RemoveFailure,
+        RemoveItem, RemoveResponse, RemoveSuccess, SessionData,
+    },
+    notify::Notifier,
+    shutdown::{BatchGuard, Shutdown},
+    utils,
+};
+
+/// GitHub asks integrators to wait at least a second between mutating
+/// requests to stay clear of its secondary rate limits.
+pub const DEFAULT_MUTATION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reason reported for repository names GitHub would not accept.
+pub const INVALID_REPO_REASON: &str = "invalid repository name";
+
+/// Reason reported for usernames GitHub would not accept.
+pub const INVALID_LOGIN_REASON: &str = "invalid username";
+
+/// Reason reported for team slugs GitHub would not accept.
+pub const INVALID_TEAM_REASON: &str = "invalid team slug";
+
+/// Reason reported for items skipped because the drain deadline passed.
+pub const UNPROCESSED_REASON: &str = "not attempted: server shutting down";
+
+/// Why one item was not removed, before it is reported with its item.
+#[derive(Debug)]
+struct Failure {
+    kind: FailureKind,
+    reason: String,
+}
+
+impl Failure {
+    fn new(kind: FailureKind, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            reason: reason.into(),
+        }
+    }
+
+    fn unprocessed() -> Self {
+        Self::new(FailureKind::Shutdown, UNPROCESSED_REASON)
+    }
+}
+
+/// Limits on removals that apply on top of GitHub's own permission checks.
+#[derive(Debug, Clone, Default)]
+pub struct RemovalPolicy {
+    /// Logins that are never removed, such as deploy bots or break-glass
+    /// accounts. Matched case-insensitively, as GitHub logins are.
+    pub protected_collaborators: Vec<String>,
+    /// Largest batch accepted in one request; unlimited when `None`.
+    pub max_batch_size: Option<usize>,
+    /// Repositories whose collaborators are only removed through an approved
+    /// proposal; `*` covers every repository.
+    pub approval_required_repos: Vec<String>,
+}
+
+impl RemovalPolicy {
+    fn is_protected(&self, username: &str) -> bool {
+        self.protected_collaborators
+            .iter()
+            .any(|login| login.eq_ignore_ascii_case(username))
+    }
+
+    fn requires_approval(&self, repo: &str) -> bool {
+        self.approval_required_repos
+            .iter()
+            .any(|name| name == "*" || name.eq_ignore_ascii_case(repo))
+    }
+}
+
+/// Result of one item in a removal batch, as reported to progress callbacks.
+#[derive(Debug, Clone, Copy)]
+pub enum ItemOutcome<'a> {
+    Removed(&'a RemoveSuccess),
+    Failed(&'a RemoveFailure),
+}
+
+/// Access the session user holds on a repository named in a removal batch.
+#[derive(Debug, Clone, Copy, Default)]
+struct RepoAccess {
+    owned: bool,
+    admin: bool,
+}
+
+/// Runs each user's mutating GitHub requests one at a time, spaced by
+/// `interval`. Reads may run concurrently, but GitHub's secondary rate limits
+/// punish concurrent or rapid writes from one user. Those limits are per user,
+/// so different users never wait on each other.
+struct Pacer {
+    interval: Duration,
+    /// When each user's next request may start, keyed by lowercased login.
+    slots: Mutex<HashMap<String, Arc<AsyncMutex<Option<Instant>>>>>,
+}
+
+impl Pacer {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            slots: Mutex::default(),
+        }
+    }
+
+    fn slot(&self, user: &str) -> Arc<AsyncMutex<Option<Instant>>> {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget users with nothing queued whose interval has passed, so the
+        // map only holds recently active users.
+        let now = Instant::now();
+        slots.retain(|_, slot| {
+            Arc::strong_count(slot) > 1
+                || slot
+                    .try_lock()
+                    .is_ok_and(|next| next.is_some_and(|at| at > now))
+        });
+        slots.entry(user.to_lowercase()).or_default().clone()
+    }
+
+    /// Sends `request` once `user`'s slot is free, or returns `None` without
+    /// sending it if the drain deadline passed while waiting.
+    async fn run<T>(
+        &self,
+        user: &str,
+        shutdown: &Shutdown,
+        request: impl Future<Output = T>,
+    ) -> Option<T> {
+        let slot = self.slot(user);
+        let mut next = slot.lock().await;
+        if let Some(at) = *next {
+            sleep_until(at).await;
+        }
+        if shutdown.is_expired() {
+            return None;
+        }
+        let output = request.await;
+        *next = Some(Instant::now() + self.interval);
+        Some(output)
+    }
+}
+
+/// Collaborator removal with every safeguard applied, independent of how the
+/// request arrived. The web handlers and `audit-cli` both go through here, so
+/// a batch is validated, access-checked and audited the same way everywhere.
+#[derive(Clone)]
+pub struct AuditService {
+    github: Arc<dyn GitHubApi>,
+    audit: AuditTrail,
+    policy: Arc<RemovalPolicy>,
+    notifier: Notifier,
+    shutdown: Shutdown,
+    /// Repositories worked on at once within a batch.
+    max_concurrency: usize,
+    pacer: Arc<Pacer>,
+}
+
+impl AuditService {
+    pub fn new(github: Arc<dyn GitHubApi>, audit: AuditTrail) -> Self {
+        Self {
+            github,
+            audit,
+            policy: Arc::default(),
+            notifier: Notifier::default(),
+            shutdown: Shutdown::default(),
+            max_concurrency: 4,
+            pacer: Arc::new(Pacer::new(DEFAULT_MUTATION_INTERVAL)),
+        }
+    }
+
+    pub fn with_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Minimum gap between the end of one DELETE and the start of the next
+    /// by the same user.
+    pub fn with_mutation_interval(mut self, interval: Duration) -> Self {
+        self.pacer = Arc::new(Pacer::new(interval));
+        self
+    }
+
+    pub fn with_policy(mut self, policy: RemovalPolicy) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Counts as an in-flight batch for shutdown draining until dropped, for
+    /// work that will call [`AuditService::remove`] later.
+    pub fn begin_batch(&self) -> BatchGuard {
+        self.shutdown.begin_batch()
+    }
+
+    /// Removes collaborators from repositories owned by the session user. Every
+    /// item is checked independently: it must not target the user themselves,
+    /// the repository must belong to the user, the user must hold admin on
+    /// it, the collaborator must not be protected by the removal policy, and
+    /// the repository must not require an approved proposal. Only an empty
+    /// or oversized batch is rejected as a whole. Items still pending when a
+    /// shutdown's drain deadline passes fail as unprocessed, and are recorded
+    /// in the audit trail like any other failure.
+    pub async fn remove(
+        &self,
+        session: &SessionData,
+        items: Vec<RemoveItem>,
+    ) -> Result<RemoveResponse, AppError> {
+        self.remove_with_progress(session, items, |_| {}).await
+    }
+
+    /// Rejects a batch that must not run at all: an empty one, or one larger
+    /// than the policy allows.
+    pub fn check_batch(&self, items: &[RemoveItem]) -> Result<(), AppError> {
+        self.check_batch_len(items.len())
+    }
+
+    fn check_batch_len(&self, len: usize) -> Result<(), AppError> {
+        if len == 0 {
+            return Err(AppError::BadRequest("items must not be empty".to_string()));
+        }
+        if let Some(max) = self.policy.max_batch_size
+            && len > max
+        {
+            return Err(AppError::BadRequest(format!(
+                "items must not contain more than {max} entries"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Like [`AuditService::remove`], calling `progress` as each item is
+    /// settled. Items on different repositories settle in any order; the
+    /// response keeps request order.
+    pub async fn remove_with_progress(
+        &self,
+        session: &SessionData,
+        items: Vec<RemoveItem>,
+        progress: impl FnMut(ItemOutcome<'_>) + Send,
+    ) -> Result<RemoveResponse, AppError> {
+        self.execute(session, &session.user_login, None, items, progress)
+            .await
+    }
+
+    /// Like [`AuditService::remove`], for repositories owned by `owner`, such
+    /// as a workspace organization, instead of the session user.
+    pub async fn remove_in(
+        &self,
+        session: &SessionData,
+        owner: &str,
+        items: Vec<RemoveItem>,
+    ) -> Result<RemoveResponse, AppError> {
+        self.execute(session, owner, None, items, |_| {}).await
+    }
+
+    /// Runs a batch proposed by `session` on `owner`'s repositories and
+    /// approved by `approver`, as [`AuditService::remove_in`] does. The
+    /// approval lifts the policy's approval requirement; every other check
+    /// still applies to the proposer, and audit events name both users.
+    pub async fn remove_approved(
+        &self,
+        session: &SessionData,
+        owner: &str,
+        approver: &str,
+        items: Vec<RemoveItem>,
+    ) -> Result<RemoveResponse, AppError> {
+        self.execute(session, owner, Some(approver), items, |_| {})
+            .await
+    }
+
+    /// Fails with `Forbidden` unless `approver` is someone other than
+    /// `proposer` and holds admin on every repository in `items`, which
+    /// belong to `owner`.
+    pub async fn check_approver(
+        &self,
+        approver: &SessionData,
+        proposer: &str,
+        owner: &str,
+        items: &[RemoveItem],
+    ) -> Result<(), AppError> {
+        if approver.user_login.eq_ignore_ascii_case(proposer) {
+            warn!(
+                user = approver.user_login,
+                "proposer tried to decide their own removal proposal"
+            );
+            return Err(AppError::Forbidden);
+        }
+
+        let mut repos: Vec<&str> = items.iter().map(|item| item.repo.as_str()).collect();
+        repos.sort_unstable();
+        repos.dedup();
+        for repo in repos {
+            let permission = self
+                .github
+                .fetch_effective_permission(
+                    &approver.access_token,
+                    owner,
+                    repo,
+                    &approver.user_login,
+                )
+                .await?;
+            if !permission.is_some_and(|p| GitHubClient::is_admin_permission(&p)) {
+                warn!(
+                    user = approver.user_login,
+                    owner, repo, "approver lacks admin on a proposed repository"
+                );
+                return Err(AppError::Forbidden);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes outside collaborators from every repository of `org` through
+    /// the organization endpoint. Each login is checked and reported on its
+    /// own, with `org` in place of the repository. As this reaches every
+    /// repository at once, it is refused whenever the policy requires
+    /// approval for any repository.
+    pub async fn remove_outside_collaborators(
+        &self,
+        session: &SessionData,
+        org: &str,
+        usernames: Vec<String>,
+    ) -> Result<RemoveResponse, AppError> {
+        if usernames.is_empty() {
+            return Err(AppError::BadRequest(
+                "usernames must not be empty".to_string(),
+            ));
+        }
+        if let Some(max) = self.policy.max_batch_size
+            && usernames.len() > max
+        {
+            return Err(AppError::BadRequest(format!(
+                "usernames must not contain more than {max} entries"
+            )));
+        }
+
+        let _batch = self.shutdown.begin_batch();
+        let mut success = Vec::new();
+        let mut failed = Vec::new();
+        for username in usernames {
+            let username = username.trim().to_owned();
+            let outcome = if username.is_empty() {
+                Err(Failure::new(
+                    FailureKind::Invalid,
+                    "username must be non-empty",
+                ))
+            } else if !utils::is_valid_login(&username) {
+                Err(Failure::new(FailureKind::Invalid, INVALID_LOGIN_REASON))
+            } else if self.shutdown.is_expired() {
+                Err(Failure::unprocessed())
+            } else if username.eq_ignore_ascii_case(&session.user_login) {
+                Err(Failure::new(
+                    FailureKind::SelfRemoval,
+                    "cannot remove authenticated user",
+                ))
+            } else if self.policy.is_protected(&username) {
+                Err(Failure::new(
+                    FailureKind::Protected,
+                    "collaborator is protected by policy",
+                ))
+            } else if !self.policy.approval_required_repos.is_empty() {
+                Err(Failure::new(
+                    FailureKind::ApprovalRequired,
+                    "removal requires an approved proposal",
+                ))
+            } else {
+                self.remove_outside_one(session, org, &username).await
+            };
+
+            let item = RemoveItem {
+                repo: org.to_owned(),
+                username,
+            };
+            match self.record(session, None, "outside_collaborator_remove", item, outcome) {
+                Ok(removed) => success.push(removed),
+                Err(failure) => failed.push(failure),
+            }
+        }
+
+        let response = RemoveResponse { success, failed };
+        self.notifier
+            .collaborators_removed(&session.user_login, &response);
+        Ok(response)
+    }
+
+    /// Deletes deploy keys from repositories owned by the session user. Each
+    /// item gets the ownership and admin checks of [`AuditService::remove`]
+    /// and is reported on its own. `approval_required_repos` does not apply:
+    /// it guards people's access, and proposals only carry collaborators, so
+    /// keys on those repositories could otherwise never be revoked.
+    pub async fn remove_deploy_keys(
+        &self,
+        session: &SessionData,
+        items: Vec<DeployKeyItem>,
+    ) -> Result<DeployKeyRemoveResponse, AppError> {
+        self.check_batch_len(items.len())?;
+
+        let _batch = self.shutdown.begin_batch();
+        let owner = session.user_login.as_str();
+        let mut access_by_repo: HashMap<String, RepoAccess> = HashMap::new();
+        let mut success = Vec::new();
+        let mut failed = Vec::new();
+        for item in items {
+            let outcome = if item.repo.trim().is_empty() {
+                Err(Failure::new(FailureKind::Invalid, "repo must be non-empty"))
+            } else if !utils::is_valid_repo_name(&item.repo) {
+                Err(Failure::new(FailureKind::Invalid, INVALID_REPO_REASON))
+            } else if self.shutdown.is_expired() {
+                Err(Failure::unprocessed())
+            } else {
+                let access = match access_by_repo.get(&item.repo) {
+                    Some(access) => *access,
+                    None => {
+                        let access = self.repo_access(session, owner, &item.repo).await;
+                        access_by_repo.insert(item.repo.clone(), access);
+                        access
+                    }
+                };
+                // Exempt from approval, see above.
+                match self.repo_precondition(session, owner, access, true, &item.repo) {
+                    Some(failure) => Err(failure),
+                    None => self.remove_deploy_key(session, &item).await,
+                }
+            };
+
+            let detail = format!("{}/keys/{}", item.repo, item.key_id);
+            let removals = &metrics::metrics().deploy_key_removals;
+            match outcome {
+                Ok(()) => {
+                    removals.with_label_values(&["success", ""]).inc();
+                    self.audit.record(
+                        AuditEvent::new(
+                            &session.user_login,
+                            "deploy_key_remove",
+                            AuditOutcome::Success,
+                        )
+                        .with_detail(detail),
+                    );
+                    success.push(item);
+                }
+                Err(Failure { kind, reason }) => {
+                    removals.with_label_values(&["failure", kind.label()]).inc();
+                    self.audit.record(
+                        AuditEvent::new(
+                            &session.user_login,
+                            "deploy_key_remove",
+                            AuditOutcome::Failure,
+                        )
+                        .with_detail(format!("{detail}: {reason}")),
+                    );
+                    failed.push(DeployKeyFailure {
+                        repo: item.repo,
+                        key_id: item.key_id,
+                        reason,
+                        kind,
+                    });
+                }
+            }
+        }
+
+        let response = DeployKeyRemoveResponse { success, failed };
+        self.notifier
+            .deploy_keys_removed(&session.user_login, &response);
+        Ok(response)
+    }
+
+    /// Revokes `team`'s access to the organization repository `org/repo`.
+    /// Refused for repositories whose removals need an approved proposal.
+    pub async fn remove_team_repo(
+        &self,
+        session: &SessionData,
+        org: &str,
+        team: &str,
+        repo: &str,
+    ) -> Result<(), AppError> {
+        let refusal = if !utils::is_valid_team_slug(team) {
+            Some(INVALID_TEAM_REASON.to_string())
+        } else if !utils::is_valid_repo_name(repo) {
+            Some(INVALID_REPO_REASON.to_string())
+        } else if self.policy.requires_approval(repo) {
+            Some("removal requires an approved proposal".to_string())
+        } else {
+            None
+        };
+        info!(org, team, repo, "attempting team repository removal");
+        self.team_change(
+            session,
+            "team_repo_remove",
+            format!("{org}/{team}: {repo}"),
+            refusal,
+            self.github
+                .remove_team_repo(&session.access_token, org, team, repo),
+        )
+        .await
+    }
+
+    /// Removes `username` from `team`. The same login rules as collaborator
+    /// removal apply: never the authenticated user or a protected login.
+    pub async fn remove_team_member(
+        &self,
+        session: &SessionData,
+        org: &str,
+        team: &str,
+        username: &str,
+    ) -> Result<(), AppError> {
+        let refusal = if !utils::is_valid_team_slug(team) {
+            Some(INVALID_TEAM_REASON.to_string())
+        } else if !utils::is_valid_login(username) {
+            Some(INVALID_LOGIN_REASON.to_string())
+        } else if username.eq_ignore_ascii_case(&session.user_login) {
+            Some("cannot remove authenticated user".to_string())
+        } else if self.policy.is_protected(username) {
+            Some("collaborator is protected by policy".to_string())
+        } else {
+            None
+        };
+        info!(org, team, username, "attempting team member removal");
+        self.team_change(
+            session,
+            "team_member_remove",
+            format!("{org}/{team}: {username}"),
+            refusal,
+            self.github
+                .remove_team_member(&session.access_token, org, team, username),
+        )
+        .await
+    }
+
+    /// Sends one team DELETE through the pacer unless `refusal` is set, and
+    /// records the outcome in the audit trail, failures included. Counts as
+    /// an in-flight batch for shutdown draining.
+    async fn team_change(
+        &self,
+        session: &SessionData,
+        action: &'static str,
+        detail: String,
+        refusal: Option<String>,
+        request: impl Future<Output = Result<StatusCode, AppError>>,
+    ) -> Result<(), AppError> {
+        let _batch = self.shutdown.begin_batch();
+        let result = match refusal {
+            Some(reason) => Err(AppError::Unprocessable(reason)),
+            None => match self
+                .pacer
+                .run(&session.user_login, &self.shutdown, request)
+                .await
+            {
+                None => Err(AppError::Unprocessable(UNPROCESSED_REASON.to_string())),
+                Some(Err(err)) => Err(err),
+                Some(Ok(StatusCode::NO_CONTENT)) => Ok(()),
+                Some(Ok(StatusCode::FORBIDDEN)) => Err(AppError::Forbidden),
+                Some(Ok(StatusCode::NOT_FOUND)) => {
+                    Err(AppError::NotFound(format!("not found: {detail}")))
+                }
+                Some(Ok(status)) => Err(AppError::Upstream(format!(
+                    "unexpected response status: {status}"
+                ))),
+            },
+        };
+
+        let event = match &result {
+            Ok(()) => AuditEvent::new(&session.user_login, action, AuditOutcome::Success)
+                .with_detail(detail),
+            Err(err) => AuditEvent::new(&session.user_login, action, AuditOutcome::Failure)
+                .with_detail(format!("{detail}: {err}")),
+        };
+        self.audit.record(event);
+        result
+    }
+
+    async fn execute(
+        &self,
+        session: &SessionData,
+        owner: &str,
+        approver: Option<&str>,
+        items: Vec<RemoveItem>,
+        progress: impl FnMut(ItemOutcome<'_>) + Send,
+    ) -> Result<RemoveResponse, AppError> {
+        self.check_batch(&items)?;
+
+        let _batch = self.shutdown.begin_batch();
+        let progress = Mutex::new(progress);
+        let report = |outcome: ItemOutcome<'_>| {
+            (progress.lock().unwrap_or_else(|e| e.into_inner()))(outcome);
+        };
+        let (items, mut failed) = Self::validate(items);
+        failed.iter().for_each(|f| report(ItemOutcome::Failed(f)));
+
+        // One group per repository, in request order, so removals from the
+        // same repository never race each other.
+        let mut groups: Vec<(String, Vec<(usize, RemoveItem)>)> = Vec::new();
+        for (index, item) in items.into_iter().enumerate() {
+            match groups.iter_mut().find(|(repo, _)| *repo == item.repo) {
+                Some((_, group)) => group.push((index, item)),
+                None => groups.push((item.repo.clone(), vec![(index, item)])),
+            }
+        }
+
+        let report = &report;
+        let mut settled = stream::iter(groups.into_iter().map(|(repo, items)| {
+            let span = info_span!("remove_repo", repo = %repo);
+
+            async move {
+                let access = self.repo_access(session, owner, &repo).await;
+
+                let mut settled = Vec::with_capacity(items.len());
+                for (index, item) in items {
+                    let outcome = if self.shutdown.is_expired() {
+                        Err(Failure::unprocessed())
+                    } else {
+                        match self.precondition(session, owner, access, approver.is_some(), &item) {
+                            Some(failure) => Err(failure),
+                            None => self.remove_one(session, owner, &item).await,
+                        }
+                    };
+                    let result =
+                        self.record(session, approver, "collaborator_remove", item, outcome);
+                    match &result {
+                        Ok(removed) => report(ItemOutcome::Removed(removed)),
+                        Err(failure) => report(ItemOutcome::Failed(failure)),
+                    }
+                    settled.push((index, result));
+                }
+                settled
+            }
+            .instrument(span)
+        }))
+        .buffer_unordered(self.max_concurrency)
+        .concat()
+        .await;
+        settled.sort_by_key(|(index, _)| *index);
+
+        let mut success = Vec::new();
+        for (_, result) in settled {
+            match result {
+                Ok(removed) => success.push(removed),
+                Err(failure) => failed.push(failure),
+            }
+        }
+
+        let unprocessed = failed
+            .iter()
+            .filter(|f| f.kind == FailureKind::Shutdown)
+            .map(|f| format!("{}/{}", f.repo, f.username))
+            .collect::<Vec<_>>();
+        if !unprocessed.is_empty() {
+            error!(
+                user = session.user_login,
+                count = unprocessed.len(),
+                items = unprocessed.join(", "),
+                "removal batch cut short by shutdown, items left unprocessed"
+            );
+        }
+
+        let response = RemoveResponse { success, failed };
+        self.notifier
+            .collaborators_removed(&session.user_login, &response);
+        Ok(response)
+    }
+
+    /// Counts the outcome of one item in metrics and the audit trail.
+    fn record(
+        &self,
+        session: &SessionData,
+        approver: Option<&str>,
+        action: &'static str,
+        item: RemoveItem,
+        outcome: Result<(), Failure>,
+    ) -> Result<RemoveSuccess, RemoveFailure> {
+        let removals = &metrics::metrics().removals;
+        match outcome {
+            Ok(()) => {
+                removals.with_label_values(&["success", ""]).inc();
+                self.audit.record(
+                    AuditEvent::new(&session.user_login, action, AuditOutcome::Success)
+                        .with_detail(format!("{}/{}", item.repo, item.username))
+                        .with_approver(approver),
+                );
+                Ok(RemoveSuccess {
+                    repo: item.repo,
+                    username: item.username,
+                })
+            }
+            Err(Failure { kind, reason }) => {
+                removals.with_label_values(&["failure", kind.label()]).inc();
+                self.audit.record(
+                    AuditEvent::new(&session.user_login, action, AuditOutcome::Failure)
+                        .with_detail(format!("{}/{}: {reason}", item.repo, item.username))
+                        .with_approver(approver),
+                );
+                Err(RemoveFailure {
+                    repo: item.repo,
+                    username: item.username,
+                    reason,
+                    kind,
+                })
+            }
+        }
+    }
+
+    /// Splits out items with a blank repository or username, or a repository
+    /// name or login GitHub would not accept. These are reported as failures
+    /// and never reach the self, policy or GitHub checks.
+    fn validate(items: Vec<RemoveItem>) -> (Vec<RemoveItem>, Vec<RemoveFailure>) {
+        let mut valid = Vec::new();
+        let mut failed = Vec::new();
+        for item in items {
+            let reason = if item.repo.trim().is_empty() || item.username.trim().is_empty() {
+                "repo and username must be non-empty"
+            } else if !utils::is_valid_repo_name(&item.repo) {
+                INVALID_REPO_REASON
+            } else if !utils::is_valid_login(&item.username) {
+                INVALID_LOGIN_REASON
+            } else {
+                valid.push(item);
+                continue;
+            };
+            failed.push(RemoveFailure {
+                repo: item.repo,
+                username: item.username,
+                reason: reason.to_string(),
+                kind: FailureKind::Invalid,
+            });
+        }
+        (valid, failed)
+    }
+
+    /// Looks up ownership and admin permission on one repository. Lookup
+    /// errors are treated as "no access" so its items fail closed.
+    async fn repo_access(&self, session: &SessionData, owner: &str, repo: &str) -> RepoAccess {
+        let owned = match self
+            .github
+            .repo_exists_for_owner(&session.access_token, owner, repo)
+            .await
+        {
+            Ok(value) => value,
+            Err(err) => {
+                warn!(repo, error = %err, "ownership validation failed");
+                false
+            }
+        };
+
+        let admin = owned
+            && match self
+                .github
+                .fetch_effective_permission(&session.access_token, owner, repo, &session.user_login)
+                .await
+            {
+                Ok(Some(permission)) => GitHubClient::is_admin_permission(&permission),
+                Ok(None) => false,
+                Err(err) => {
+                    warn!(repo, error = %err, "admin check failed");
+                    false
+                }
+            };
+
+        RepoAccess { owned, admin }
+    }
+
+    /// Returns why an item must not be attempted, if it must not.
+    fn precondition(
+        &self,
+        session: &SessionData,
+        owner: &str,
+        access: RepoAccess,
+        approved: bool,
+        item: &RemoveItem,
+    ) -> Option<Failure> {
+        if item.username.eq_ignore_ascii_case(&session.user_login) {
+            return Some(Failure::new(
+                FailureKind::SelfRemoval,
+                "cannot remove authenticated user",
+            ));
+        }
+        if self.policy.is_protected(&item.username) {
+            return Some(Failure::new(
+                FailureKind::Protected,
+                "collaborator is protected by policy",
+            ));
+        }
+        self.repo_precondition(session, owner, access, approved, &item.repo)
+    }
+
+    /// The checks of [`Self::precondition`] that concern the repository alone.
+    fn repo_precondition(
+        &self,
+        session: &SessionData,
+        owner: &str,
+        access: RepoAccess,
+        approved: bool,
+        repo: &str,
+    ) -> Option<Failure> {
+        if !access.owned {
+            let reason = if owner.eq_ignore_ascii_case(&session.user_login) {
+                "repository is not owned by authenticated user".to_string()
+            } else {
+                format!("repository is not owned by {owner}")
+            };
+            return Some(Failure::new(FailureKind::NotOwner, reason));
+        }
+        if !access.admin {
+            return Some(Failure::new(
+                FailureKind::NotAdmin,
+                "authenticated user does not have admin permission",
+            ));
+        }
+        if !approved && self.policy.requires_approval(repo) {
+            return Some(Failure::new(
+                FailureKind::ApprovalRequired,
+                "removal requires an approved proposal",
+            ));
+        }
+        None
+    }
+
+    async fn remove_deploy_key(
+        &self,
+        session: &SessionData,
+        item: &DeployKeyItem,
+    ) -> Result<(), Failure> {
+        info!(
+            repo = item.repo,
+            key_id = item.key_id,
+            "attempting deploy key deletion"
+        );
+        let status = self
+            .pacer
+            .run(
+                &session.user_login,
+                &self.shutdown,
+                self.github.remove_deploy_key(
+                    &session.access_token,
+                    &session.user_login,
+                    &item.repo,
+                    item.key_id,
+                ),
+            )
+            .await
+            .ok_or_else(Failure::unprocessed)?
+            .map_err(|err| {
+                warn!(repo = item.repo, key_id = item.key_id, error = %err, "deploy key deletion request failed");
+                Failure::new(FailureKind::Upstream, "upstream request failed")
+            })?;
+
+        match status {
+            StatusCode::NOT_FOUND => {
+                Err(Failure::new(FailureKind::NotFound, "deploy key not found"))
+            }
+            status => status_failure(status).map_or(Ok(()), Err),
+        }
+    }
+
+    async fn remove_outside_one(
+        &self,
+        session: &SessionData,
+        org: &str,
+        username: &str,
+    ) -> Result<(), Failure> {
+        info!(org, username, "attempting outside collaborator removal");
+        let status = self
+            .pacer
+            .run(
+                &session.user_login,
+                &self.shutdown,
+                self.github
+                    .remove_outside_collaborator(&session.access_token, org, username),
+            )
+            .await
+            .ok_or_else(Failure::unprocessed)?
+            .map_err(|err| {
+                warn!(org, username, error = %err, "outside collaborator removal request failed");
+                Failure::new(FailureKind::Upstream, "upstream request failed")
+            })?;
+
+        match status {
+            StatusCode::UNPROCESSABLE_ENTITY => Err(Failure::new(
+                FailureKind::OrgMember,
+                "user is an organization member, not an outside collaborator",
+            )),
+            status => status_failure(status).map_or(Ok(()), Err),
+        }
+    }
+
+    async fn remove_one(
+        &self,
+        session: &SessionData,
+        owner: &str,
+        item: &RemoveItem,
+    ) -> Result<(), Failure> {
+        info!(
+            repo = item.repo,
+            username = item.username,
+            "attempting collaborator deletion"
+        );
+        let status = self
+            .pacer
+            .run(
+                &session.user_login,
+                &self.shutdown,
+                self.github.remove_collaborator(
+                    &session.access_token,
+                    owner,
+                    &item.repo,
+                    &item.username,
+                ),
+            )
+            .await
+            .ok_or_else(Failure::unprocessed)?
+            .map_err(|err| {
+                warn!(repo = item.repo, username = item.username, error = %err, "collaborator deletion request failed");
+                Failure::new(FailureKind::Upstream, "upstream request failed")
+            })?;
+
+        status_failure(status).map_or(Ok(()), Err)
+    }
+}
+
+/// Maps the status of a collaborator DELETE to a failure, or `None` when the
+/// collaborator was removed.
+fn status_failure(status: StatusCode) -> Option<Failure> {
+    match status {
+        StatusCode::NO_CONTENT => None,
+        StatusCode::FORBIDDEN => Some(Failure::new(
+            FailureKind::Forbidden,
+            "insufficient permissions",
+        )),
+        StatusCode::UNPROCESSABLE_ENTITY => Some(Failure::new(
+            FailureKind::Unprocessable,
+            "validation failed or abuse detection triggered",
+        )),
+        StatusCode::NOT_FOUND => Some(Failure::new(
+            FailureKind::NotFound,
+            "collaborator not found",
+        )),
+        other => Some(Failure::new(
+            FailureKind::UnexpectedStatus,
+            format!("unexpected response status: {other}"),
+        )),
+    }
+}
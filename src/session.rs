@@ -0,0 +1,364 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use axum_extra::extract::cookie::Key;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::Utc;
+use rand::RngCore;
+use rusqlite::{Connection, OptionalExtension, params};
+use tracing::{info, warn};
+
+use crate::{error::AppError, models::SessionData, utils};
+
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    pub session: SessionData,
+    pub created_at: i64,
+    pub last_seen_at: i64,
+}
+
+/// Persistence for server-side sessions, keyed by an opaque session ID that is
+/// the only thing stored in the browser cookie.
+pub trait SessionStore: Send + Sync {
+    fn insert(&self, id: &str, record: &SessionRecord) -> Result<(), AppError>;
+    fn get(&self, id: &str) -> Result<Option<SessionRecord>, AppError>;
+    fn touch(&self, id: &str, last_seen_at: i64) -> Result<(), AppError>;
+    fn remove(&self, id: &str) -> Result<(), AppError>;
+    fn remove_user(&self, user_login: &str) -> Result<usize, AppError>;
+    fn remove_expired(&self, idle_before: i64, created_before: i64) -> Result<usize, AppError>;
+}
+
+#[derive(Clone)]
+pub struct Sessions {
+    store: Arc<dyn SessionStore>,
+    idle_timeout: Duration,
+    absolute_timeout: Duration,
+}
+
+impl Sessions {
+    pub fn new(
+        store: Arc<dyn SessionStore>,
+        idle_timeout: Duration,
+        absolute_timeout: Duration,
+    ) -> Self {
+        Self {
+            store,
+            idle_timeout,
+            absolute_timeout,
+        }
+    }
+
+    pub fn create(&self, session: SessionData) -> Result<String, AppError> {
+        let id = utils::random_token(48);
+        let now = Utc::now().timestamp();
+        self.store.insert(
+            &id,
+            &SessionRecord {
+                session,
+                created_at: now,
+                last_seen_at: now,
+            },
+        )?;
+        Ok(id)
+    }
+
+    /// Loads a live session and refreshes its idle timer. Expired sessions are
+    /// deleted on sight rather than waiting for the periodic purge.
+    pub fn load(&self, id: &str) -> Result<Option<SessionData>, AppError> {
+        let Some(record) = self.store.get(id)? else {
+            return Ok(None);
+        };
+
+        let now = Utc::now().timestamp();
+        if self.is_expired(&record, now) {
+            self.store.remove(id)?;
+            return Ok(None);
+        }
+
+        self.store.touch(id, now)?;
+        Ok(Some(record.session))
+    }
+
+    /// Whether `id` is a live session of `user_login`. Unlike `load`, this
+    /// does not count as activity, so checking on someone else's behalf does
+    /// not keep their session alive.
+    pub fn is_live(&self, id: &str, user_login: &str) -> Result<bool, AppError> {
+        let now = Utc::now().timestamp();
+        Ok(self.store.get(id)?.is_some_and(|record| {
+            !self.is_expired(&record, now) && record.session.user_login == user_login
+        }))
+    }
+
+    pub fn revoke(&self, id: &str) -> Result<(), AppError> {
+        self.store.remove(id)
+    }
+
+    pub fn revoke_user(&self, user_login: &str) -> Result<usize, AppError> {
+        self.store.remove_user(user_login)
+    }
+
+    pub fn purge_expired(&self) -> Result<usize, AppError> {
+        let now = Utc::now().timestamp();
+        self.store.remove_expired(
+            now - self.idle_timeout.as_secs() as i64,
+            now - self.absolute_timeout.as_secs() as i64,
+        )
+    }
+
+    /// Periodically deletes expired sessions so abandoned logins do not pile up.
+    pub fn spawn_purge_task(&self, every: Duration) {
+        let sessions = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                match sessions.purge_expired() {
+                    Ok(0) => {}
+                    Ok(purged) => info!(purged, "purged expired sessions"),
+                    Err(err) => warn!(error = %err, "session purge failed"),
+                }
+            }
+        });
+    }
+
+    fn is_expired(&self, record: &SessionRecord, now: i64) -> bool {
+        now - record.last_seen_at > self.idle_timeout.as_secs() as i64
+            || now - record.created_at > self.absolute_timeout.as_secs() as i64
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySessionStore {
+    records: Mutex<HashMap<String, SessionRecord>>,
+}
+
+impl InMemorySessionStore {
+    fn records(&self) -> Result<MutexGuard<'_, HashMap<String, SessionRecord>>, AppError> {
+        self.records.lock().map_err(|_| AppError::Internal)
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    fn insert(&self, id: &str, record: &SessionRecord) -> Result<(), AppError> {
+        self.records()?.insert(id.to_owned(), record.clone());
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<SessionRecord>, AppError> {
+        Ok(self.records()?.get(id).cloned())
+    }
+
+    fn touch(&self, id: &str, last_seen_at: i64) -> Result<(), AppError> {
+        if let Some(record) = self.records()?.get_mut(id) {
+            record.last_seen_at = last_seen_at;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), AppError> {
+        self.records()?.remove(id);
+        Ok(())
+    }
+
+    fn remove_user(&self, user_login: &str) -> Result<usize, AppError> {
+        let mut records = self.records()?;
+        let before = records.len();
+        records.retain(|_, record| record.session.user_login != user_login);
+        Ok(before - records.len())
+    }
+
+    fn remove_expired(&self, idle_before: i64, created_before: i64) -> Result<usize, AppError> {
+        let mut records = self.records()?;
+        let before = records.len();
+        records.retain(|_, record| {
+            record.last_seen_at >= idle_before && record.created_at >= created_before
+        });
+        Ok(before - records.len())
+    }
+}
+
+const NONCE_LEN: usize = 12;
+
+/// Keeps sessions in SQLite. The `data` column, which holds the GitHub access
+/// token, is sealed with AES-256-GCM under the cookie key and bound to the
+/// session ID, so the database file alone does not give the tokens away.
+pub struct SqliteSessionStore {
+    conn: Mutex<Connection>,
+    cipher: Aes256Gcm,
+}
+
+impl SqliteSessionStore {
+    pub fn open(path: &Path, key: &Key) -> Result<Self, AppError> {
+        let conn = Connection::open(path).map_err(|e| {
+            AppError::Config(format!(
+                "failed to open session database {}: {e}",
+                path.display()
+            ))
+        })?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                user_login TEXT NOT NULL,
+                data TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                last_seen_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS sessions_user_login ON sessions (user_login);",
+        )
+        .map_err(|e| AppError::Config(format!("failed to initialize session database: {e}")))?;
+
+        let cipher = Aes256Gcm::new_from_slice(key.encryption())
+            .map_err(|_| AppError::Config("invalid session encryption key".to_string()))?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            cipher,
+        })
+    }
+
+    fn seal(&self, id: &str, session: &SessionData) -> Result<String, AppError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let plaintext = serde_json::to_vec(session)?;
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: id.as_bytes(),
+                },
+            )
+            .map_err(|_| AppError::Internal)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(STANDARD.encode(sealed))
+    }
+
+    /// `None` when the row cannot be decrypted, such as after the session
+    /// secret changed; the session is then treated as gone.
+    fn open_sealed(&self, id: &str, data: &str) -> Option<SessionData> {
+        let sealed = STANDARD.decode(data).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: id.as_bytes(),
+                },
+            )
+            .ok()?;
+        serde_json::from_slice(&plaintext).ok()
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, AppError> {
+        self.conn.lock().map_err(|_| AppError::Internal)
+    }
+}
+
+impl SessionStore for SqliteSessionStore {
+    fn insert(&self, id: &str, record: &SessionRecord) -> Result<(), AppError> {
+        let data = self.seal(id, &record.session)?;
+        self.conn()?
+            .execute(
+                "INSERT OR REPLACE INTO sessions (id, user_login, data, created_at, last_seen_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    id,
+                    record.session.user_login,
+                    data,
+                    record.created_at,
+                    record.last_seen_at
+                ],
+            )
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<SessionRecord>, AppError> {
+        let row = self
+            .conn()?
+            .query_row(
+                "SELECT data, created_at, last_seen_at FROM sessions WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(storage_error)?;
+
+        let Some((data, created_at, last_seen_at)) = row else {
+            return Ok(None);
+        };
+        let Some(session) = self.open_sealed(id, &data) else {
+            warn!("session row could not be decrypted, discarding it");
+            self.remove(id)?;
+            return Ok(None);
+        };
+
+        Ok(Some(SessionRecord {
+            session,
+            created_at,
+            last_seen_at,
+        }))
+    }
+
+    fn touch(&self, id: &str, last_seen_at: i64) -> Result<(), AppError> {
+        self.conn()?
+            .execute(
+                "UPDATE sessions SET last_seen_at = ?2 WHERE id = ?1",
+                params![id, last_seen_at],
+            )
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), AppError> {
+        self.conn()?
+            .execute("DELETE FROM sessions WHERE id = ?1", params![id])
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn remove_user(&self, user_login: &str) -> Result<usize, AppError> {
+        self.conn()?
+            .execute(
+                "DELETE FROM sessions WHERE user_login = ?1",
+                params![user_login],
+            )
+            .map_err(storage_error)
+    }
+
+    fn remove_expired(&self, idle_before: i64, created_before: i64) -> Result<usize, AppError> {
+        self.conn()?
+            .execute(
+                "DELETE FROM sessions WHERE last_seen_at < ?1 OR created_at < ?2",
+                params![idle_before, created_before],
+            )
+            .map_err(storage_error)
+    }
+}
+
+fn storage_error(err: rusqlite::Error) -> AppError {
+    warn!(error = %err, "session storage error");
+    AppError::Internal
+}
@@ -0,0 +1,134 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{sync::watch, time::timeout};
+use tracing::{info, warn};
+
+/// Coordinates a graceful shutdown with removal batches still running. Once
+/// shutdown is requested, batches get `drain_timeout` to finish; after that
+/// they stop before their next item and report the rest as unprocessed.
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    drain_timeout: Duration,
+    requested: watch::Sender<bool>,
+    batches: watch::Sender<usize>,
+    expired: AtomicBool,
+}
+
+/// Marks a removal batch as in flight until dropped.
+pub struct BatchGuard {
+    inner: Arc<Inner>,
+}
+
+impl Drop for BatchGuard {
+    fn drop(&mut self) {
+        self.inner.batches.send_modify(|n| *n -= 1);
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl Shutdown {
+    pub fn new(drain_timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                drain_timeout,
+                requested: watch::Sender::new(false),
+                batches: watch::Sender::new(0),
+                expired: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn request(&self) {
+        self.inner.requested.send_replace(true);
+    }
+
+    /// Resolves once shutdown has been requested.
+    pub async fn requested(&self) {
+        let mut requested = self.inner.requested.subscribe();
+        // The sender lives in `self`, so this cannot fail.
+        let _ = requested.wait_for(|requested| *requested).await;
+    }
+
+    pub fn begin_batch(&self) -> BatchGuard {
+        self.inner.batches.send_modify(|n| *n += 1);
+        BatchGuard {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Whether the drain deadline has passed and batches should stop.
+    pub fn is_expired(&self) -> bool {
+        self.inner.expired.load(Ordering::Acquire)
+    }
+
+    /// Waits for shutdown to be requested, then for in-flight batches to
+    /// finish. Past the drain deadline batches are told to stop, and this
+    /// returns once they have.
+    pub async fn drain(&self) {
+        self.requested().await;
+
+        let in_flight = *self.inner.batches.borrow();
+        if in_flight > 0 {
+            info!(
+                in_flight,
+                drain_timeout_secs = self.inner.drain_timeout.as_secs(),
+                "waiting for removal batches to finish"
+            );
+        }
+
+        let mut batches = self.inner.batches.subscribe();
+        let finished = timeout(self.inner.drain_timeout, batches.wait_for(|n| *n == 0))
+            .await
+            .is_ok();
+        if !finished {
+            warn!("drain deadline reached, stopping removal batches");
+            self.inner.expired.store(true, Ordering::Release);
+            let _ = batches.wait_for(|n| *n == 0).await;
+        }
+    }
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM where available.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!(error = %err, "failed to listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(err) => {
+                warn!(error = %err, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
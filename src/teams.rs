@@ -0,0 +1,150 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tracing::{Instrument, info, info_span};
+use utoipa::ToSchema;
+
+use crate::{
+    error::AppError,
+    github::GitHubApi,
+    models::{Permissions, Repository},
+};
+
+/// An organization team as GitHub lists it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Team {
+    pub id: u64,
+    pub slug: String,
+    pub name: String,
+    /// Set for nested teams.
+    #[serde(default)]
+    pub parent: Option<TeamParent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TeamParent {
+    pub slug: String,
+}
+
+/// A repository a team has access to, with the team's permission on it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TeamRepository {
+    #[serde(flatten)]
+    pub repo: Repository,
+    #[serde(default)]
+    pub permissions: Permissions,
+    pub role_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeamRole {
+    Member,
+    Maintainer,
+}
+
+impl TeamRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Maintainer => "maintainer",
+        }
+    }
+}
+
+/// One team with everyone and everything it grants access to.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TeamAudit {
+    pub slug: String,
+    pub name: String,
+    pub parent: Option<String>,
+    /// Slugs of teams nested directly under this one.
+    pub child_teams: Vec<String>,
+    pub maintainers: Vec<String>,
+    /// Members who are not maintainers. As GitHub reports them, this
+    /// includes members of child teams.
+    pub members: Vec<String>,
+    pub repos: Vec<TeamRepoAccess>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TeamRepoAccess {
+    pub repo: String,
+    /// `admin`, `maintain`, `write`, `triage` or `read`.
+    pub permission: String,
+    /// Custom repository role, when the team was granted one.
+    pub role_name: Option<String>,
+}
+
+/// Lists the teams of `org` with their members, maintainers, repositories
+/// and nesting. Teams are looked up at most `max_concurrency` at a time.
+pub async fn audit_org_teams(
+    api: &dyn GitHubApi,
+    token: &str,
+    org: &str,
+    max_concurrency: usize,
+) -> Result<Vec<TeamAudit>, AppError> {
+    let teams = api.fetch_org_teams(token, org).await?;
+    let semaphore = Arc::new(Semaphore::new(max_concurrency.max(1)));
+
+    let mut audits = stream::iter(teams.into_iter().map(|team| {
+        let semaphore = semaphore.clone();
+        let span = info_span!("audit_team", org, team = %team.slug);
+
+        async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .map_err(|_| AppError::Internal)?;
+            let (maintainers, members, repos) = tokio::try_join!(
+                api.fetch_team_members(token, org, &team.slug, TeamRole::Maintainer),
+                api.fetch_team_members(token, org, &team.slug, TeamRole::Member),
+                api.fetch_team_repos(token, org, &team.slug),
+            )?;
+
+            Ok::<_, AppError>(TeamAudit {
+                slug: team.slug,
+                name: team.name,
+                parent: team.parent.map(|parent| parent.slug),
+                child_teams: Vec::new(),
+                maintainers: maintainers.into_iter().map(|u| u.login).collect(),
+                members: members.into_iter().map(|u| u.login).collect(),
+                repos: repos
+                    .into_iter()
+                    .map(|r| TeamRepoAccess {
+                        repo: r.repo.name,
+                        permission: r.permissions.label().to_string(),
+                        role_name: r.role_name,
+                    })
+                    .collect(),
+            })
+        }
+        .instrument(span)
+    }))
+    .buffer_unordered(max_concurrency.max(1))
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let mut children: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for audit in &audits {
+        if let Some(parent) = &audit.parent {
+            children
+                .entry(parent.clone())
+                .or_default()
+                .push(audit.slug.clone());
+        }
+    }
+    for audit in &mut audits {
+        if let Some(mut child_teams) = children.remove(&audit.slug) {
+            child_teams.sort();
+            audit.child_teams = child_teams;
+        }
+    }
+    audits.sort_by(|a, b| a.slug.cmp(&b.slug));
+
+    info!(org, team_count = audits.len(), "fetched teams");
+    Ok(audits)
+}
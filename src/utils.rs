@@ -1,47 +1,102 @@
-use std::env;
+use std::{env, io};
 
+use crate::redact::Redacting;
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
 use rand::{Rng, distributions::Alphanumeric, rngs::ThreadRng};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
-use url::Url;
 
-use crate::error::AppError;
-
-#[derive(Clone)]
-pub struct AppConfig {
-    pub github_client_id: String,
-    pub github_client_secret: String,
-    pub session_secret: String,
-    pub base_url: Url,
-    pub max_concurrency: usize,
+/// Flushes spans still buffered for OTLP export when dropped, so hold it for
+/// the lifetime of the process.
+pub struct TracingGuard {
+    provider: Option<SdkTracerProvider>,
 }
 
-impl AppConfig {
-    pub fn from_env() -> Result<Self, AppError> {
-        let github_client_id = require_env("GITHUB_CLIENT_ID")?;
-        let github_client_secret = require_env("GITHUB_CLIENT_SECRET")?;
-        let session_secret = require_env("SESSION_SECRET")?;
-        let base_url = Url::parse(&require_env("BASE_URL")?)
-            .map_err(|e| AppError::Config(format!("invalid BASE_URL: {e}")))?;
-
-        Ok(Self {
-            github_client_id,
-            github_client_secret,
-            session_secret,
-            base_url,
-            max_concurrency: 10,
-        })
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take()
+            && let Err(err) = provider.shutdown()
+        {
+            eprintln!("failed to flush traces: {err}");
+        }
     }
 }
 
-pub fn require_env(key: &str) -> Result<String, AppError> {
-    env::var(key).map_err(|_| AppError::Config(format!("missing required env var: {key}")))
-}
+/// Logs to stdout as text, or as JSON lines with `LOG_FORMAT=json`, and
+/// exports spans over OTLP/HTTP when an `OTEL_EXPORTER_OTLP_ENDPOINT` (or
+/// `..._TRACES_ENDPOINT`) is configured. The exporter reads the standard
+/// `OTEL_*` variables itself.
+pub fn init_tracing() -> TracingGuard {
+    let provider = otlp_tracer_provider();
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("learning-rust"))
+    });
+
+    let json = match env::var("LOG_FORMAT").as_deref() {
+        Err(_) | Ok("text") => false,
+        Ok("json") => true,
+        Ok(other) => {
+            eprintln!("unknown LOG_FORMAT {other:?}, using text");
+            false
+        }
+    };
+    // Every line goes through the redacting writer, whichever format is used.
+    let writer = || Redacting(io::stdout);
+    let (text_layer, json_layer) = if json {
+        (None, Some(fmt::layer().json().with_writer(writer())))
+    } else {
+        (Some(fmt::layer().with_writer(writer())), None)
+    };
 
-pub fn init_tracing() {
     tracing_subscriber::registry()
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
-        .with(fmt::layer())
+        .with(text_layer)
+        .with(json_layer)
+        .with(otel_layer)
         .init();
+
+    TracingGuard { provider }
+}
+
+fn otlp_tracer_provider() -> Option<SdkTracerProvider> {
+    if env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none()
+        && env::var_os("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_none()
+    {
+        return None;
+    }
+
+    // Tracing is not initialised yet, so failures can only go to stderr.
+    let exporter = match SpanExporter::builder().with_http().build() {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            eprintln!("OTLP export disabled, failed to build exporter: {err}");
+            return None;
+        }
+    };
+
+    let mut resource = Resource::builder();
+    if env::var_os("OTEL_SERVICE_NAME").is_none() {
+        resource = resource.with_service_name("collaborator-audit-dashboard");
+    }
+
+    Some(
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource.build())
+            .build(),
+    )
+}
+
+/// Trace ID of the current span, when spans are being exported.
+pub fn current_trace_id() -> Option<String> {
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
 }
 
 pub fn random_token(len: usize) -> String {
@@ -65,3 +120,35 @@ pub fn parse_next_link(link_header: Option<&str>) -> Option<String> {
     }
     None
 }
+
+/// Whether `name` could be a GitHub repository name: ASCII letters, digits,
+/// `.`, `_` and `-`, neither starting with `.` nor containing `..`. Names go
+/// into API paths, where anything else could point a request elsewhere.
+pub fn is_valid_repo_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 100
+        && !name.starts_with('.')
+        && !name.contains("..")
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'))
+}
+
+/// Whether `login` could be a GitHub login: at most 39 ASCII letters, digits
+/// and single hyphens, neither starting nor ending with a hyphen.
+pub fn is_valid_login(login: &str) -> bool {
+    !login.is_empty()
+        && login.len() <= 39
+        && !login.starts_with('-')
+        && !login.ends_with('-')
+        && !login.contains("--")
+        && login
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// Whether `slug` could be a GitHub team slug. Slugs are derived from team
+/// names under the same character rules as repository names.
+pub fn is_valid_team_slug(slug: &str) -> bool {
+    is_valid_repo_name(slug)
+}
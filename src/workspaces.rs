@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// App-level role of a workspace member. Each role includes the ones before
+/// it, so an approver may also remove and an admin may do everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// View and export the workspace audit.
+    Viewer,
+    /// Remove collaborators and submit removal proposals.
+    Remover,
+    /// Approve or reject removal proposals.
+    Approver,
+    /// See the member list.
+    Admin,
+}
+
+/// An audit scope shared by several app users, defined under `[[workspaces]]`
+/// in the config file. Members act with their own GitHub token; the role only
+/// limits what the app lets them do on top of GitHub's own permissions.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    pub name: String,
+    /// Organization whose repositories the workspace audits.
+    pub org: String,
+    /// GitHub login to role.
+    #[serde(default)]
+    pub members: BTreeMap<String, Role>,
+}
+
+impl Workspace {
+    /// Role of `login`, if a member. GitHub logins are case-insensitive.
+    pub fn role_of(&self, login: &str) -> Option<Role> {
+        self.members
+            .iter()
+            .find(|(member, _)| member.eq_ignore_ascii_case(login))
+            .map(|(_, role)| *role)
+    }
+}
+
+/// A workspace as listed to one of its members.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WorkspaceMembership {
+    pub name: String,
+    pub org: String,
+    pub role: Role,
+}
+
+/// Request extension set by `middleware::require_role` on workspace routes.
+#[derive(Debug, Clone)]
+pub struct WorkspaceAccess {
+    pub workspace: Workspace,
+    pub role: Role,
+}
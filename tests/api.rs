@@ -0,0 +1,383 @@
+mod common;
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn audit_lists_external_collaborators() {
+    let base = common::spawn_app(common::fake()).await;
+
+    let rows: Value = reqwest::Client::new()
+        .get(format!("{base}/api/v1/audit?ignore_forks=true"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let rows = rows.as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    let website = rows
+        .iter()
+        .find(|row| row["repo"]["name"] == "website")
+        .unwrap();
+    let logins: Vec<&str> = website["collaborators"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["login"].as_str().unwrap())
+        .collect();
+    assert_eq!(logins, ["alice", "bob"]);
+    assert_eq!(website["can_remove"], true);
+}
+
+#[tokio::test]
+async fn rejects_unknown_bearer_token() {
+    let base = common::spawn_app(common::fake()).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{base}/api/v1/repos"))
+        .bearer_auth("wrong-token")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn remove_with_bearer_token_skips_csrf() {
+    let github = common::fake();
+    let base = common::spawn_app(github.clone()).await;
+
+    let response: Value = reqwest::Client::new()
+        .post(format!("{base}/api/v1/remove"))
+        .bearer_auth(common::TOKEN)
+        .json(&json!({ "items": [{ "repo": "website", "username": "bob" }] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response["success"][0]["username"], "bob");
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice"]
+    );
+}
+
+#[tokio::test]
+async fn deploy_keys_are_listed_and_removed_in_bulk() {
+    let github = common::fake();
+    let base = common::spawn_app(github.clone()).await;
+    let client = reqwest::Client::new();
+
+    let rows: Value = client
+        .get(format!("{base}/api/v1/deploy-keys"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let keys_of = |name: &str| {
+        rows.as_array()
+            .unwrap()
+            .iter()
+            .find(|row| row["repo"]["name"] == name)
+            .unwrap()["keys"]
+            .clone()
+    };
+    assert_eq!(
+        keys_of("website"),
+        json!([
+            {
+                "id": 11,
+                "title": "ci",
+                "read_only": true,
+                "created_at": "2024-03-01T09:00:00Z",
+                "last_used": "2024-06-01T12:00:00Z"
+            },
+            {
+                "id": 12,
+                "title": "old deploy bot",
+                "read_only": false,
+                "created_at": "2021-05-04T10:30:00Z",
+                "last_used": null
+            }
+        ])
+    );
+    assert_eq!(keys_of("locked"), Value::Null);
+
+    let response: Value = client
+        .post(format!("{base}/api/v1/deploy-keys/remove"))
+        .bearer_auth(common::TOKEN)
+        .json(&json!({ "items": [
+            { "repo": "website", "key_id": 12 },
+            { "repo": "website", "key_id": 99 },
+            { "repo": "someone-elses", "key_id": 1 }
+        ] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response["success"],
+        json!([{ "repo": "website", "key_id": 12 }])
+    );
+    assert_eq!(response["failed"][0]["reason"], "deploy key not found");
+    assert_eq!(
+        response["failed"][1]["reason"],
+        "repository is not owned by authenticated user"
+    );
+    assert_eq!(github.deploy_key_ids("octocat", "website"), [11]);
+}
+
+#[tokio::test]
+async fn repeated_idempotency_key_replays_the_first_response() {
+    let github = common::fake();
+    let base = common::spawn_app(github.clone()).await;
+    let client = reqwest::Client::new();
+    let send = |body: Value, key: &'static str| {
+        client
+            .post(format!("{base}/api/v1/remove"))
+            .bearer_auth(common::TOKEN)
+            .header("Idempotency-Key", key)
+            .json(&body)
+            .send()
+    };
+    let body = json!({ "items": [{ "repo": "website", "username": "bob" }] });
+
+    let first = send(body.clone(), "retry-1").await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("idempotent-replayed").is_none());
+    let first: Value = first.json().await.unwrap();
+
+    let retry = send(body.clone(), "retry-1").await.unwrap();
+    assert_eq!(retry.status(), StatusCode::OK);
+    assert_eq!(retry.headers()["idempotent-replayed"], "true");
+    assert_eq!(retry.json::<Value>().await.unwrap(), first);
+    assert_eq!(first["success"][0]["username"], "bob");
+    assert_eq!(first["failed"], json!([]));
+
+    let other_body = json!({ "items": [{ "repo": "website", "username": "alice" }] });
+    let reused = send(other_body, "retry-1").await.unwrap();
+    assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice"]
+    );
+
+    let invalid = send(body, "has space").await.unwrap();
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn background_remove_reports_progress_through_job() {
+    let github = common::fake();
+    let base = common::spawn_app(github.clone()).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{base}/api/v1/remove"))
+        .bearer_auth(common::TOKEN)
+        .json(&json!({
+            "items": [
+                { "repo": "website", "username": "bob" },
+                { "repo": "website", "username": "nobody" }
+            ],
+            "background": true
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let accepted: Value = response.json().await.unwrap();
+    let status_url = accepted["status_url"].as_str().unwrap();
+    assert!(status_url.starts_with("/api/v1/jobs/"));
+
+    let mut job = Value::Null;
+    for _ in 0..50 {
+        job = client
+            .get(format!("{base}{status_url}"))
+            .bearer_auth(common::TOKEN)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(job["status"], "completed");
+    assert_eq!(job["total"], 2);
+    assert_eq!(job["processed"], 2);
+    assert_eq!(job["result"]["success"][0]["username"], "bob");
+    assert_eq!(job["result"]["failed"][0]["username"], "nobody");
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice"]
+    );
+
+    let missing = client
+        .get(format!("{base}/api/v1/jobs/unknown"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn demo_mode_signs_in_without_oauth() {
+    let base = common::spawn_demo_app().await;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let anonymous = client
+        .get(format!("{base}/dashboard"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(anonymous.status(), StatusCode::SEE_OTHER);
+
+    let login = client
+        .get(format!("{base}/auth/login"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(login.headers()["location"], "/dashboard");
+    let cookie = login.headers()["set-cookie"]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let dashboard = client
+        .get(format!("{base}/dashboard"))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(dashboard.contains("Demo mode"));
+    assert!(dashboard.contains("contractor-amy"));
+    let csrf = dashboard
+        .split(r#"name="csrf-token" content=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap()
+        .to_string();
+
+    let body = json!({ "items": [{ "repo": "marketing-site", "username": "intern-cy" }] });
+    let without_csrf = client
+        .post(format!("{base}/remove"))
+        .header("cookie", &cookie)
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(without_csrf.status(), StatusCode::FORBIDDEN);
+
+    let response: Value = client
+        .post(format!("{base}/remove"))
+        .header("cookie", &cookie)
+        .header("x-csrf-token", csrf)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(response["success"][0]["username"], "intern-cy");
+}
+
+#[tokio::test]
+async fn configured_token_is_only_used_when_presented() {
+    let github = common::fake();
+    let mut config = common::config();
+    config.github_token = Some(common::TOKEN.to_string());
+    let base = common::spawn_app_with(config, github.clone()).await;
+    let client = reqwest::Client::new();
+
+    let anonymous = client
+        .get(format!("{base}/api/v1/repos"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+    let removal = client
+        .post(format!("{base}/remove"))
+        .json(&json!({ "items": [{ "repo": "website", "username": "bob" }] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(removal.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice", "bob"]
+    );
+
+    let bearer = client
+        .get(format!("{base}/api/v1/repos"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bearer.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn echoes_or_assigns_request_id() {
+    let base = common::spawn_app(common::fake()).await;
+    let client = reqwest::Client::new();
+
+    let supplied = client
+        .get(format!("{base}/health"))
+        .header("x-request-id", "deploy-check-42")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(supplied.headers()["x-request-id"], "deploy-check-42");
+
+    let generated = client
+        .get(format!("{base}/health"))
+        .header("x-request-id", "spaces and/slashes")
+        .send()
+        .await
+        .unwrap();
+    let id = generated.headers()["x-request-id"].to_str().unwrap();
+    assert_eq!(id.len(), 16);
+    assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+}
+
+#[tokio::test]
+async fn metrics_are_only_served_when_enabled() {
+    let mut config = common::config();
+    config.metrics_enabled = false;
+    let base = common::spawn_app_with(config, common::fake()).await;
+
+    let response = reqwest::get(format!("{base}/metrics")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,116 @@
+#![allow(dead_code)]
+
+pub mod stub;
+
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use learning_rust::{
+    AppState, app,
+    config::{AppConfig, SessionStoreConfig},
+    github::{GitHubApi, RetryPolicy},
+    github_fake::{FakeGitHub, Fixture},
+    models::SessionData,
+    service::RemovalPolicy,
+};
+use tokio::net::TcpListener;
+use url::Url;
+
+pub const TOKEN: &str = "test-token";
+pub const VIEWER: &str = "octocat";
+
+pub fn fake() -> Arc<FakeGitHub> {
+    Arc::new(FakeGitHub::new(fixture()))
+}
+
+/// The fixture behind `fake`, for tests that add to it.
+pub fn fixture() -> Fixture {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/account.json");
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).expect("fixture loads")
+}
+
+pub fn session() -> SessionData {
+    SessionData {
+        access_token: TOKEN.to_string(),
+        user_login: VIEWER.to_string(),
+        csrf_token: "csrf".to_string(),
+        granted_scopes: Some("repo, read:org".to_string()),
+    }
+}
+
+pub fn config() -> AppConfig {
+    AppConfig {
+        github_client_id: "client-id".to_string(),
+        github_client_secret: "client-secret".to_string(),
+        session_secret: "test-session-secret".to_string(),
+        base_url: Url::parse("http://localhost:3000").unwrap(),
+        bind_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        drain_timeout: Duration::from_secs(5),
+        github_api_url: Url::parse("https://api.github.com").unwrap(),
+        github_web_url: Url::parse("https://github.com").unwrap(),
+        max_concurrency: 4,
+        mutation_interval: Duration::ZERO,
+        retry: RetryPolicy::default(),
+        github_app: None,
+        github_token: None,
+        session_store: SessionStoreConfig::Memory,
+        session_idle_timeout: Duration::from_secs(3600),
+        session_absolute_timeout: Duration::from_secs(86400),
+        revoke_token_on_logout: false,
+        metrics_enabled: true,
+        audit_log_path: None,
+        demo_fixture: None,
+        allowed_users: Vec::new(),
+        removal_policy: RemovalPolicy::default(),
+        max_running_jobs: 2,
+        notify_webhooks: Vec::new(),
+        workspaces: Vec::new(),
+    }
+}
+
+/// Serves the app exactly as `main` does, with the REST API and OAuth
+/// endpoints pointed at `github_url`.
+pub async fn spawn_app_against(github_url: &str) -> String {
+    spawn_app_against_with(config(), github_url).await
+}
+
+/// Like `spawn_app_against`, with a config adjusted by the test.
+pub async fn spawn_app_against_with(mut config: AppConfig, github_url: &str) -> String {
+    config.github_api_url = Url::parse(github_url).unwrap();
+    config.github_web_url = Url::parse(github_url).unwrap();
+    let state = AppState::from_config(config).await.expect("state builds");
+    serve(state).await
+}
+
+/// Serves the app in demo mode from `fixtures/demo.json`.
+pub async fn spawn_demo_app() -> String {
+    let mut config = config();
+    config.demo_fixture = Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/demo.json"));
+    let state = AppState::from_config(config).await.expect("state builds");
+    serve(state).await
+}
+
+/// Serves the app backed by `github` on an ephemeral port and returns its base URL.
+pub async fn spawn_app(github: Arc<dyn GitHubApi>) -> String {
+    let state = AppState::with_github(config(), github)
+        .await
+        .expect("state builds");
+    serve(state).await
+}
+
+/// Like `spawn_app`, with a config adjusted by the test.
+pub async fn spawn_app_with(config: AppConfig, github: Arc<dyn GitHubApi>) -> String {
+    let state = AppState::with_github(config, github)
+        .await
+        .expect("state builds");
+    serve(state).await
+}
+
+/// Serves `state` on an ephemeral port, for tests that seed it first.
+pub async fn serve(state: AppState) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app(state)).await.unwrap();
+    });
+    format!("http://{addr}")
+}
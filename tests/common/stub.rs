@@ -0,0 +1,492 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    Form, Json, Router,
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+
+pub const STUB_TOKEN: &str = "stub-token";
+pub const GOOD_CODE: &str = "good-code";
+const PAGE_SIZE: usize = 2;
+
+/// A repository as the stub serves it. Collaborators other than the owner are
+/// granted `write`.
+#[derive(Clone)]
+pub struct StubRepo {
+    pub name: &'static str,
+    pub collaborators: Vec<&'static str>,
+    /// Permission the viewer holds; `admin` on their own repositories.
+    pub viewer_permission: &'static str,
+    /// Answer collaborator listing with a plain 403.
+    pub forbidden: bool,
+    /// Answer the first collaborator listing with a rate-limited 403.
+    pub rate_limited_once: bool,
+    /// Status of every collaborator DELETE, overriding 204/404.
+    pub delete_status: Option<StatusCode>,
+    /// Answer every collaborator DELETE with a rate-limited 403.
+    pub delete_rate_limited: bool,
+    /// Hold every collaborator DELETE this long before answering.
+    pub delete_delay: Option<Duration>,
+}
+
+impl StubRepo {
+    pub fn new(name: &'static str, collaborators: &[&'static str]) -> Self {
+        Self {
+            name,
+            collaborators: collaborators.to_vec(),
+            viewer_permission: "admin",
+            forbidden: false,
+            rate_limited_once: false,
+            delete_status: None,
+            delete_rate_limited: false,
+            delete_delay: None,
+        }
+    }
+}
+
+struct StubState {
+    base: String,
+    viewer: &'static str,
+    repos: Mutex<Vec<StubRepo>>,
+    /// Organizations of the viewer, and whether each still needs SAML SSO
+    /// authorization for the token.
+    orgs: Mutex<Vec<(&'static str, bool)>>,
+    /// Status of `DELETE /applications/{client_id}/token`.
+    revoke_status: Mutex<StatusCode>,
+    /// Tokens revoked through the OAuth app, in order.
+    revoked: Mutex<Vec<String>>,
+    hits: Mutex<HashMap<String, usize>>,
+}
+
+/// Minimal stand-in for GitHub's REST API and OAuth endpoints, served on an
+/// ephemeral port. Lists are paginated two items at a time with `Link`
+/// headers, like GitHub does at `per_page` boundaries.
+#[derive(Clone)]
+pub struct StubGitHub {
+    state: Arc<StubState>,
+}
+
+impl StubGitHub {
+    pub async fn start(viewer: &'static str, repos: Vec<StubRepo>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(StubState {
+            base,
+            viewer,
+            repos: Mutex::new(repos),
+            orgs: Mutex::new(Vec::new()),
+            revoke_status: Mutex::new(StatusCode::NO_CONTENT),
+            revoked: Mutex::new(Vec::new()),
+            hits: Mutex::new(HashMap::new()),
+        });
+
+        let router = Router::new()
+            .route("/login/oauth/access_token", post(access_token))
+            .route("/user", get(user))
+            .route("/user/repos", get(user_repos))
+            .route("/user/orgs", get(user_orgs))
+            .route("/orgs/{org}/repos", get(org_repos))
+            .route("/rate_limit", get(rate_limit))
+            .route(
+                "/applications/{client_id}/token",
+                axum::routing::delete(revoke_token),
+            )
+            .route("/repos/{owner}/{repo}", get(repo))
+            .route("/repos/{owner}/{repo}/collaborators", get(collaborators))
+            .route(
+                "/repos/{owner}/{repo}/collaborators/{username}",
+                axum::routing::delete(remove_collaborator),
+            )
+            .route(
+                "/repos/{owner}/{repo}/collaborators/{username}/permission",
+                get(permission),
+            )
+            .layer(middleware::from_fn_with_state(state.clone(), count_hits))
+            .with_state(state.clone());
+
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        Self { state }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.state.base
+    }
+
+    /// Number of requests served for `method path`, e.g. `GET /user/repos`.
+    pub fn hits(&self, route: &str) -> usize {
+        self.state
+            .hits
+            .lock()
+            .unwrap()
+            .get(route)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Adds an organization of the viewer; with `sso_required`, its API
+    /// answers 403 with an `X-GitHub-SSO` header as GitHub does.
+    pub fn add_org(&self, login: &'static str, sso_required: bool) {
+        self.state.orgs.lock().unwrap().push((login, sso_required));
+    }
+
+    pub fn set_revoke_status(&self, status: StatusCode) {
+        *self.state.revoke_status.lock().unwrap() = status;
+    }
+
+    /// Tokens revoked so far with the app's client credentials.
+    pub fn revoked_tokens(&self) -> Vec<String> {
+        self.state.revoked.lock().unwrap().clone()
+    }
+
+    pub fn collaborators(&self, repo: &str) -> Vec<&'static str> {
+        self.state
+            .repos
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|r| r.name == repo)
+            .map(|r| r.collaborators.clone())
+            .unwrap_or_default()
+    }
+}
+
+async fn count_hits(State(state): State<Arc<StubState>>, request: Request, next: Next) -> Response {
+    let key = format!("{} {}", request.method(), request.uri().path());
+    *state.hits.lock().unwrap().entry(key).or_default() += 1;
+    next.run(request).await
+}
+
+fn authorized(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        == Some(&format!("Bearer {STUB_TOKEN}"))
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(json!({ "message": "Bad credentials" })),
+    )
+        .into_response()
+}
+
+fn rate_limited() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        [("retry-after", "1"), ("x-ratelimit-remaining", "0")],
+        Json(json!({ "message": "API rate limit exceeded" })),
+    )
+        .into_response()
+}
+
+/// Serves one page of `items`, with a `Link: rel="next"` header when more remain.
+fn paginate(
+    base: &str,
+    path: &str,
+    query: &HashMap<String, String>,
+    items: Vec<Value>,
+) -> Response {
+    let page = query
+        .get("page")
+        .and_then(|p| p.parse::<usize>().ok())
+        .unwrap_or(1);
+    let start = (page - 1) * PAGE_SIZE;
+    let slice = items
+        .iter()
+        .skip(start)
+        .take(PAGE_SIZE)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut headers = HeaderMap::new();
+    if start + PAGE_SIZE < items.len() {
+        let next = format!(
+            "<{base}{path}?per_page=100&page={}>; rel=\"next\"",
+            page + 1
+        );
+        headers.insert(header::LINK, next.parse().unwrap());
+    }
+    (headers, Json(slice)).into_response()
+}
+
+fn repo_json(owner: &str, repo: &StubRepo, id: usize) -> Value {
+    json!({
+        "id": id,
+        "name": repo.name,
+        "owner": { "login": owner },
+        "private": false,
+        "archived": false,
+        "fork": false,
+    })
+}
+
+async fn access_token(Form(form): Form<HashMap<String, String>>) -> Json<Value> {
+    if form.get("code").map(String::as_str) != Some(GOOD_CODE) {
+        return Json(json!({
+            "error": "bad_verification_code",
+            "error_description": "The code passed is incorrect or expired."
+        }));
+    }
+    Json(json!({
+        "access_token": STUB_TOKEN,
+        "token_type": "bearer",
+        "scope": "repo,read:org",
+    }))
+}
+
+async fn user(State(state): State<Arc<StubState>>, headers: HeaderMap) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    (
+        [("x-oauth-scopes", "repo, read:org")],
+        Json(json!({ "login": state.viewer })),
+    )
+        .into_response()
+}
+
+async fn user_orgs(State(state): State<Arc<StubState>>, headers: HeaderMap) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let orgs = state.orgs.lock().unwrap();
+    Json(
+        orgs.iter()
+            .map(|(login, _)| json!({ "login": login }))
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
+}
+
+async fn org_repos(
+    State(state): State<Arc<StubState>>,
+    Path(org): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let orgs = state.orgs.lock().unwrap();
+    match orgs.iter().find(|(login, _)| *login == org) {
+        Some((_, true)) => (
+            StatusCode::FORBIDDEN,
+            [(
+                "x-github-sso",
+                format!("required; url=https://github.com/orgs/{org}/sso?authorization_request=1"),
+            )],
+            Json(json!({ "message": "Resource protected by organization SAML enforcement." })),
+        )
+            .into_response(),
+        Some((_, false)) => Json(json!([])).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn rate_limit(headers: HeaderMap) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let reset = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 600;
+    Json(json!({
+        "resources": {
+            "core": { "limit": 5000, "remaining": 4321, "used": 679, "reset": reset }
+        }
+    }))
+    .into_response()
+}
+
+/// Only accepts the client credentials `common::config` uses.
+async fn revoke_token(
+    State(state): State<Arc<StubState>>,
+    Path(client_id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    use base64::Engine;
+    let expected = format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode("client-id:client-secret")
+    );
+    if client_id != "client-id"
+        || headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            != Some(&expected)
+    {
+        return unauthorized();
+    }
+    let status = *state.revoke_status.lock().unwrap();
+    if status == StatusCode::NO_CONTENT
+        && let Some(token) = body["access_token"].as_str()
+    {
+        state.revoked.lock().unwrap().push(token.to_string());
+    }
+    status.into_response()
+}
+
+async fn user_repos(
+    State(state): State<Arc<StubState>>,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let items = state
+        .repos
+        .lock()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(id, repo)| repo_json(state.viewer, repo, id + 1))
+        .collect();
+    paginate(&state.base, "/user/repos", &query, items)
+}
+
+async fn repo(
+    State(state): State<Arc<StubState>>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let repos = state.repos.lock().unwrap();
+    match repos.iter().enumerate().find(|(_, r)| r.name == name) {
+        Some((id, repo)) if owner == state.viewer => {
+            Json(repo_json(&owner, repo, id + 1)).into_response()
+        }
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn collaborators(
+    State(state): State<Arc<StubState>>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let items = {
+        let mut repos = state.repos.lock().unwrap();
+        let Some(repo) = repos.iter_mut().find(|r| r.name == name) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        if repo.rate_limited_once {
+            repo.rate_limited_once = false;
+            return rate_limited();
+        }
+        if repo.forbidden {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(
+                    json!({ "message": "Must have push access to view repository collaborators." }),
+                ),
+            )
+                .into_response();
+        }
+        std::iter::once(owner.as_str())
+            .chain(repo.collaborators.iter().copied())
+            .enumerate()
+            .map(|(id, login)| {
+                let admin = login == owner;
+                json!({
+                    "login": login,
+                    "id": id + 1,
+                    "permissions": { "admin": admin, "push": true, "pull": true },
+                    "role_name": if admin { "admin" } else { "write" },
+                })
+            })
+            .collect()
+    };
+    paginate(
+        &state.base,
+        &format!("/repos/{owner}/{name}/collaborators"),
+        &query,
+        items,
+    )
+}
+
+async fn permission(
+    State(state): State<Arc<StubState>>,
+    headers: HeaderMap,
+    Path((_owner, name, username)): Path<(String, String, String)>,
+) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let repos = state.repos.lock().unwrap();
+    let Some(repo) = repos.iter().find(|r| r.name == name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let permission = if username == state.viewer {
+        repo.viewer_permission
+    } else if repo.collaborators.contains(&username.as_str()) {
+        "write"
+    } else {
+        "none"
+    };
+    Json(json!({
+        "permission": permission,
+        "role_name": permission,
+        "user": { "login": username },
+    }))
+    .into_response()
+}
+
+async fn remove_collaborator(
+    State(state): State<Arc<StubState>>,
+    headers: HeaderMap,
+    Path((_owner, name, username)): Path<(String, String, String)>,
+) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let delay = {
+        let repos = state.repos.lock().unwrap();
+        repos
+            .iter()
+            .find(|r| r.name == name)
+            .and_then(|r| r.delete_delay)
+    };
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    let mut repos = state.repos.lock().unwrap();
+    let Some(repo) = repos.iter_mut().find(|r| r.name == name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if repo.delete_rate_limited {
+        return rate_limited();
+    }
+    if let Some(status) = repo.delete_status {
+        return status.into_response();
+    }
+    let before = repo.collaborators.len();
+    repo.collaborators.retain(|login| *login != username);
+    if repo.collaborators.len() < before {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, fs, net::SocketAddr, path::PathBuf, time::Duration};
+
+use learning_rust::{config::AppConfig, error::AppError, workspaces::Role};
+
+const REQUIRED: &str = r#"
+[server]
+base_url = "https://audit.example.com"
+
+[github]
+client_id = "file-client-id"
+client_secret = "file-client-secret"
+
+[session]
+secret = "file-session-secret"
+"#;
+
+fn write_config(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("config-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+fn load(path: Option<&PathBuf>, env: &[(&str, &str)]) -> Result<AppConfig, AppError> {
+    let env = env
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<HashMap<_, _>>();
+    AppConfig::from_sources(path.map(PathBuf::as_path), |key| env.get(key).cloned())
+}
+
+fn config_error(result: Result<AppConfig, AppError>) -> String {
+    match result {
+        Err(AppError::Config(message)) => message,
+        Err(other) => panic!("expected a config error, got {other}"),
+        Ok(_) => panic!("expected a config error"),
+    }
+}
+
+#[test]
+fn file_values_apply_and_env_overrides_them() {
+    let path = write_config(
+        "layered.toml",
+        r#"
+[server]
+bind = "127.0.0.1"
+port = 8080
+base_url = "https://audit.example.com"
+
+[github]
+client_id = "file-client-id"
+client_secret = "file-client-secret"
+
+[github.retry]
+max_attempts = 2
+default_backoff_secs = 5
+
+[session]
+secret = "file-session-secret"
+
+[metrics]
+enabled = true
+
+[access]
+allowed_users = ["Octocat", "hubot"]
+
+[policy]
+protected_collaborators = ["deploy-bot"]
+max_batch_size = 25
+
+[notifications]
+webhooks = ["https://hooks.example.com/audit"]
+"#,
+    );
+
+    let config = load(
+        Some(&path),
+        &[("PORT", "9090"), ("GITHUB_CLIENT_ID", "env-id")],
+    )
+    .unwrap();
+
+    assert_eq!(config.bind_addr, SocketAddr::from(([127, 0, 0, 1], 9090)));
+    assert_eq!(config.github_client_id, "env-id");
+    assert_eq!(config.github_client_secret, "file-client-secret");
+    assert_eq!(config.retry.max_attempts, 2);
+    assert_eq!(config.retry.default_backoff, Duration::from_secs(5));
+    assert!(config.is_user_allowed("octocat"));
+    assert!(!config.is_user_allowed("mallory"));
+    assert_eq!(
+        config.removal_policy.protected_collaborators,
+        ["deploy-bot"]
+    );
+    assert_eq!(config.removal_policy.max_batch_size, Some(25));
+    assert_eq!(config.notify_webhooks.len(), 1);
+    assert!(config.metrics_enabled);
+}
+
+#[test]
+fn env_only_configuration_still_works() {
+    let config = load(
+        None,
+        &[
+            ("GITHUB_CLIENT_ID", "id"),
+            ("GITHUB_CLIENT_SECRET", "secret"),
+            ("SESSION_SECRET", "session-secret"),
+            ("BASE_URL", "http://localhost:3000"),
+            ("PROTECTED_COLLABORATORS", "deploy-bot, ops-bot"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(config.bind_addr, SocketAddr::from(([0, 0, 0, 0], 3000)));
+    assert_eq!(config.max_concurrency, 10);
+    assert!(!config.metrics_enabled);
+    assert_eq!(
+        config.removal_policy.protected_collaborators,
+        ["deploy-bot", "ops-bot"]
+    );
+}
+
+#[test]
+fn empty_env_lists_leave_file_lists_in_place() {
+    let path = write_config(
+        "empty-env.toml",
+        &format!(
+            "{REQUIRED}\n[access]\nallowed_users = [\"octocat\"]\n\n[policy]\nprotected_collaborators = [\"deploy-bot\"]\napproval_required_repos = [\"website\"]\n"
+        ),
+    );
+
+    let config = load(
+        Some(&path),
+        &[
+            ("ALLOWED_USERS", ""),
+            ("PROTECTED_COLLABORATORS", " "),
+            ("APPROVAL_REQUIRED_REPOS", ""),
+        ],
+    )
+    .unwrap();
+
+    assert!(!config.is_user_allowed("mallory"));
+    assert_eq!(
+        config.removal_policy.protected_collaborators,
+        ["deploy-bot"]
+    );
+    assert_eq!(config.removal_policy.approval_required_repos, ["website"]);
+}
+
+#[test]
+fn errors_name_the_key_at_fault() {
+    let path = write_config(
+        "bad-type.toml",
+        &format!("{REQUIRED}\n[github.retry]\nmax_attempts = \"lots\"\n"),
+    );
+    let message = config_error(load(Some(&path), &[]));
+    assert!(message.contains("github.retry.max_attempts"), "{message}");
+
+    let path = write_config(
+        "zero.toml",
+        &format!("{REQUIRED}\n[policy]\nmax_batch_size = 0\n"),
+    );
+    let message = config_error(load(Some(&path), &[]));
+    assert!(message.contains("policy.max_batch_size"), "{message}");
+
+    let path = write_config(
+        "webhook.toml",
+        &format!(
+            "{REQUIRED}\n[notifications]\nwebhooks = [\"https://ok.example.com\", \"ftp://nope\"]\n"
+        ),
+    );
+    let message = config_error(load(Some(&path), &[]));
+    assert!(message.contains("notifications.webhooks[1]"), "{message}");
+
+    let path = write_config(
+        "unknown.toml",
+        &format!("{REQUIRED}\n[policy]\nprotected = []\n"),
+    );
+    let message = config_error(load(Some(&path), &[]));
+    assert!(message.contains("policy.protected in"), "{message}");
+
+    let path = write_config("env.toml", REQUIRED);
+    let message = config_error(load(Some(&path), &[("MAX_CONCURRENCY", "many")]));
+    assert!(message.contains("MAX_CONCURRENCY"), "{message}");
+}
+
+#[test]
+fn missing_required_settings_name_both_sources() {
+    let message = config_error(load(None, &[]));
+    assert!(message.contains("GITHUB_CLIENT_ID"), "{message}");
+    assert!(message.contains("github.client_id"), "{message}");
+}
+
+#[test]
+fn demo_mode_refuses_credentials_from_the_file() {
+    let path = write_config("demo.toml", REQUIRED);
+    let message = config_error(load(
+        Some(&path),
+        &[("DEMO_FIXTURE_PATH", "fixtures/demo.json")],
+    ));
+    assert!(message.contains("github.client_id"), "{message}");
+}
+
+#[test]
+fn workspaces_are_read_from_the_file_and_validated() {
+    let path = write_config(
+        "workspaces.toml",
+        &format!(
+            r#"{REQUIRED}
+[[workspaces]]
+name = "platform"
+org = "octo-org"
+members = {{ octocat = "admin", hubot = "viewer" }}
+"#
+        ),
+    );
+    let config = load(Some(&path), &[]).unwrap();
+    let platform = config.workspace("platform").unwrap();
+    assert_eq!(platform.org, "octo-org");
+    assert_eq!(platform.role_of("HUBOT"), Some(Role::Viewer));
+    assert_eq!(platform.role_of("mallory"), None);
+
+    let bad_role = write_config(
+        "workspace-role.toml",
+        &format!(
+            "{REQUIRED}\n[[workspaces]]\nname = \"platform\"\norg = \"octo-org\"\nmembers = {{ octocat = \"owner\" }}\n"
+        ),
+    );
+    assert!(config_error(load(Some(&bad_role), &[])).contains("workspaces[0].members.octocat"));
+
+    let duplicate = write_config(
+        "workspace-duplicate.toml",
+        &format!(
+            "{REQUIRED}\n[[workspaces]]\nname = \"platform\"\norg = \"a\"\n\n[[workspaces]]\nname = \"platform\"\norg = \"b\"\n"
+        ),
+    );
+    assert!(config_error(load(Some(&duplicate), &[])).contains("invalid workspaces[1] in"));
+}
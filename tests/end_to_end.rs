@@ -0,0 +1,566 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::stub::{GOOD_CODE, STUB_TOKEN, StubGitHub, StubRepo};
+use reqwest::{Client, Response, StatusCode, header, redirect::Policy};
+use serde_json::{Value, json};
+use url::Url;
+
+fn client() -> Client {
+    Client::builder().redirect(Policy::none()).build().unwrap()
+}
+
+/// The `name=value` pairs a browser would send back after `response`.
+fn cookies(response: &Response) -> String {
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok()?.split(';').next())
+        .filter(|pair| !pair.ends_with('='))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn location(response: &Response) -> &str {
+    response.headers()[header::LOCATION].to_str().unwrap()
+}
+
+fn repos() -> Vec<StubRepo> {
+    vec![
+        StubRepo::new("website", &["alice", "bob"]),
+        StubRepo::new("docs", &["carol"]),
+        StubRepo {
+            forbidden: true,
+            ..StubRepo::new("locked", &["dave"])
+        },
+        StubRepo {
+            rate_limited_once: true,
+            ..StubRepo::new("throttled", &["erin"])
+        },
+        StubRepo::new("empty", &[]),
+    ]
+}
+
+async fn start() -> (StubGitHub, String) {
+    let github = StubGitHub::start("octocat", repos()).await;
+    let app = common::spawn_app_against(github.url()).await;
+    (github, app)
+}
+
+async fn begin_login(app: &str) -> (String, String) {
+    let response = client()
+        .get(format!("{app}/auth/login"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let authorize = Url::parse(location(&response)).unwrap();
+    let state = authorize
+        .query_pairs()
+        .find(|(k, _)| k == "state")
+        .map(|(_, v)| v.into_owned())
+        .unwrap();
+    (state, cookies(&response))
+}
+
+/// Signs in through the OAuth flow; returns the session cookie and the CSRF
+/// token the dashboard hands to its scripts.
+async fn sign_in(app: &str) -> (String, String) {
+    let (state, cookie) = begin_login(app).await;
+    let callback = client()
+        .get(format!("{app}/auth/callback"))
+        .query(&[("code", GOOD_CODE), ("state", state.as_str())])
+        .header(header::COOKIE, cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(location(&callback), "/dashboard");
+    let session = cookies(&callback);
+
+    let html = client()
+        .get(format!("{app}/dashboard"))
+        .header(header::COOKIE, &session)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let csrf = html
+        .split(r#"name="csrf-token" content=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap()
+        .to_string();
+    (session, csrf)
+}
+
+fn temp_audit_log() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+        "audit-{}.jsonl",
+        learning_rust::utils::random_token(12)
+    ))
+}
+
+/// The audit events with `action`, as JSON.
+fn audit_events(path: &std::path::Path, action: &str) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .filter(|event| event["action"] == action)
+        .collect()
+}
+
+/// An app that revokes the OAuth token on logout and keeps an audit log.
+async fn start_revoking() -> (StubGitHub, String, std::path::PathBuf) {
+    let github = StubGitHub::start("octocat", repos()).await;
+    let audit_log = temp_audit_log();
+    let mut config = common::config();
+    config.revoke_token_on_logout = true;
+    config.audit_log_path = Some(audit_log.clone());
+    let app = common::spawn_app_against_with(config, github.url()).await;
+    (github, app, audit_log)
+}
+
+async fn logout(app: &str, session: &str, csrf: &str) -> Response {
+    client()
+        .post(format!("{app}/logout"))
+        .header(header::COOKIE, session)
+        .header("x-csrf-token", csrf)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn oauth_login_round_trip() {
+    let (github, app) = start().await;
+
+    let response = client()
+        .get(format!("{app}/auth/login"))
+        .send()
+        .await
+        .unwrap();
+    assert!(location(&response).starts_with(&format!("{}/login/oauth/authorize?", github.url())));
+
+    let (state, cookie) = begin_login(&app).await;
+    let callback = client()
+        .get(format!("{app}/auth/callback"))
+        .query(&[("code", GOOD_CODE), ("state", state.as_str())])
+        .header(header::COOKIE, cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(callback.status(), StatusCode::SEE_OTHER);
+    assert_eq!(location(&callback), "/dashboard");
+
+    let dashboard = client()
+        .get(format!("{app}/dashboard"))
+        .header(header::COOKIE, cookies(&callback))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(dashboard.status(), StatusCode::OK);
+    let html = dashboard.text().await.unwrap();
+    assert!(html.contains("alice"));
+    assert!(!html.contains("dave"));
+}
+
+#[tokio::test]
+async fn oauth_callback_rejects_bad_state() {
+    let (_github, app) = start().await;
+    let (state, cookie) = begin_login(&app).await;
+
+    let mismatched = client()
+        .get(format!("{app}/auth/callback"))
+        .query(&[("code", GOOD_CODE), ("state", "forged")])
+        .header(header::COOKIE, cookie.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(mismatched.status(), StatusCode::UNAUTHORIZED);
+
+    let without_cookie = client()
+        .get(format!("{app}/auth/callback"))
+        .query(&[("code", GOOD_CODE), ("state", state.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(without_cookie.status(), StatusCode::UNAUTHORIZED);
+
+    let bad_code = client()
+        .get(format!("{app}/auth/callback"))
+        .query(&[("code", "expired"), ("state", state.as_str())])
+        .header(header::COOKIE, cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bad_code.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn follows_pagination_links() {
+    let (github, app) = start().await;
+
+    let repos: Vec<Value> = client()
+        .get(format!("{app}/api/v1/repos"))
+        .bearer_auth(STUB_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(repos.len(), 5);
+    assert_eq!(github.hits("GET /user/repos"), 3);
+
+    // The owner plus two collaborators spans two pages.
+    let collaborators: Vec<Value> = client()
+        .get(format!("{app}/api/v1/repos/website/collaborators"))
+        .bearer_auth(STUB_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(collaborators.len(), 3);
+    assert_eq!(github.hits("GET /repos/octocat/website/collaborators"), 2);
+}
+
+#[tokio::test]
+async fn bearer_token_is_validated_once() {
+    let (github, app) = start().await;
+
+    for _ in 0..3 {
+        let response = client()
+            .get(format!("{app}/api/v1/repos"))
+            .bearer_auth(STUB_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    assert_eq!(github.hits("GET /user"), 1);
+}
+
+#[tokio::test]
+async fn retries_after_rate_limited_403() {
+    let (github, app) = start().await;
+
+    let started = Instant::now();
+    let response = client()
+        .get(format!("{app}/api/v1/repos/throttled/collaborators"))
+        .bearer_auth(STUB_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(github.hits("GET /repos/octocat/throttled/collaborators"), 2);
+
+    let metrics = client()
+        .get(format!("{app}/metrics"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains(
+        r#"github_rate_limit_backoffs_total{endpoint="/repos/{owner}/{repo}/collaborators"}"#
+    ));
+    assert!(metrics.contains(
+        r#"github_requests_total{endpoint="/repos/{owner}/{repo}/collaborators",method="GET",status="403"}"#
+    ));
+    assert!(metrics.contains(
+        r#"github_request_duration_seconds_count{endpoint="/repos/{owner}/{repo}/collaborators",method="GET",status="403"}"#
+    ));
+}
+
+#[tokio::test]
+async fn forbidden_repo_is_left_out_of_audit() {
+    let (_github, app) = start().await;
+
+    let direct = client()
+        .get(format!("{app}/api/v1/repos/locked/collaborators"))
+        .bearer_auth(STUB_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(direct.status(), StatusCode::FORBIDDEN);
+
+    let rows: Vec<Value> = client()
+        .get(format!("{app}/api/v1/audit"))
+        .bearer_auth(STUB_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let mut names: Vec<&str> = rows
+        .iter()
+        .map(|row| row["repo"]["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["docs", "throttled", "website"]);
+}
+
+#[tokio::test]
+async fn remove_reports_every_failure_reason() {
+    let github = StubGitHub::start(
+        "octocat",
+        vec![
+            StubRepo::new("website", &["alice", "bob"]),
+            StubRepo {
+                viewer_permission: "write",
+                ..StubRepo::new("shared", &["carol"])
+            },
+            StubRepo {
+                delete_status: Some(StatusCode::FORBIDDEN),
+                ..StubRepo::new("protected", &["dave"])
+            },
+            StubRepo {
+                delete_status: Some(StatusCode::UNPROCESSABLE_ENTITY),
+                ..StubRepo::new("guarded", &["erin"])
+            },
+            StubRepo {
+                delete_status: Some(StatusCode::INTERNAL_SERVER_ERROR),
+                ..StubRepo::new("flaky", &["frank"])
+            },
+            StubRepo {
+                delete_rate_limited: true,
+                ..StubRepo::new("throttled", &["grace"])
+            },
+        ],
+    )
+    .await;
+    let app = common::spawn_app_against(github.url()).await;
+
+    let items = [
+        ("website", "alice"),
+        ("", "bob"),
+        ("website", "octocat"),
+        ("not-mine", "bob"),
+        ("shared", "carol"),
+        ("protected", "dave"),
+        ("guarded", "erin"),
+        ("website", "nobody"),
+        ("flaky", "frank"),
+        ("throttled", "grace"),
+    ];
+    let body = json!({
+        "items": items
+            .iter()
+            .map(|(repo, username)| json!({ "repo": repo, "username": username }))
+            .collect::<Vec<_>>()
+    });
+
+    let response: Value = client()
+        .post(format!("{app}/api/v1/remove"))
+        .bearer_auth(STUB_TOKEN)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response["success"],
+        json!([{ "repo": "website", "username": "alice" }])
+    );
+    let reason = |repo: &str, username: &str| {
+        response["failed"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["repo"] == repo && f["username"] == username)
+            .and_then(|f| f["reason"].as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    assert_eq!(reason("", "bob"), "repo and username must be non-empty");
+    assert_eq!(
+        reason("website", "octocat"),
+        "cannot remove authenticated user"
+    );
+    assert_eq!(
+        reason("not-mine", "bob"),
+        "repository is not owned by authenticated user"
+    );
+    assert_eq!(
+        reason("shared", "carol"),
+        "authenticated user does not have admin permission"
+    );
+    assert_eq!(reason("protected", "dave"), "insufficient permissions");
+    assert_eq!(
+        reason("guarded", "erin"),
+        "validation failed or abuse detection triggered"
+    );
+    assert_eq!(reason("website", "nobody"), "collaborator not found");
+    assert_eq!(
+        reason("flaky", "frank"),
+        "unexpected response status: 500 Internal Server Error"
+    );
+    assert_eq!(reason("throttled", "grace"), "upstream request failed");
+    assert_eq!(github.collaborators("website"), ["bob"]);
+    assert_eq!(
+        github.hits("DELETE /repos/octocat/throttled/collaborators/grace"),
+        5
+    );
+
+    let metrics = client()
+        .get(format!("{app}/metrics"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    for reason in ["not_owner", "not_admin", "unexpected_status", "upstream"] {
+        assert!(metrics.contains(&format!(
+            r#"collaborator_removals_total{{outcome="failure",reason="{reason}"}}"#
+        )));
+    }
+    assert!(!metrics.contains("Internal Server Error"));
+}
+
+#[tokio::test]
+async fn logout_revokes_the_oauth_token() {
+    let (github, app, audit_log) = start_revoking().await;
+    let (session, csrf) = sign_in(&app).await;
+
+    let response = logout(&app, &session, &csrf).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(location(&response), "/");
+    assert_eq!(github.revoked_tokens(), [STUB_TOKEN]);
+
+    let revocations = audit_events(&audit_log, "oauth_token_revoke");
+    assert_eq!(revocations.len(), 1);
+    assert_eq!(revocations[0]["actor"], "octocat");
+    assert_eq!(revocations[0]["outcome"], "success");
+    assert_eq!(audit_events(&audit_log, "logout").len(), 1);
+
+    let dashboard = client()
+        .get(format!("{app}/dashboard"))
+        .header(header::COOKIE, &session)
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(dashboard.status(), StatusCode::OK);
+    std::fs::remove_file(audit_log).ok();
+}
+
+#[tokio::test]
+async fn failed_revocation_is_reported_on_the_landing_page() {
+    let (github, app, audit_log) = start_revoking().await;
+    github.set_revoke_status(StatusCode::INTERNAL_SERVER_ERROR);
+    let (session, csrf) = sign_in(&app).await;
+
+    let response = logout(&app, &session, &csrf).await;
+    assert_eq!(location(&response), "/?revocation=failed");
+    assert!(github.revoked_tokens().is_empty());
+
+    let landing = client()
+        .get(format!("{app}/?revocation=failed"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(landing.contains("GitHub did not confirm that the access token was revoked"));
+
+    let revocations = audit_events(&audit_log, "oauth_token_revoke");
+    assert_eq!(revocations.len(), 1);
+    assert_eq!(revocations[0]["outcome"], "failure");
+    // Signed out all the same.
+    assert_eq!(audit_events(&audit_log, "logout").len(), 1);
+    std::fs::remove_file(audit_log).ok();
+}
+
+#[tokio::test]
+async fn logout_without_csrf_token_keeps_the_grant() {
+    let (github, app, audit_log) = start_revoking().await;
+    let (session, _) = sign_in(&app).await;
+
+    let response = logout(&app, &session, "forged").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(github.revoked_tokens().is_empty());
+    std::fs::remove_file(audit_log).ok();
+}
+
+#[tokio::test]
+async fn diagnostics_explain_missing_repositories() {
+    let (github, app) = start().await;
+    github.add_org("acme", false);
+    github.add_org("initech", true);
+    let (session, _) = sign_in(&app).await;
+
+    let response = client()
+        .get(format!("{app}/diagnostics"))
+        .header(header::COOKIE, session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = response.text().await.unwrap();
+
+    assert!(html.contains("All required scopes present"));
+    let row = |org: &str| {
+        html.split("<tr>")
+            .find(|row| row.contains(&format!("<td>{org}</td>")))
+            .unwrap_or_default()
+            .to_string()
+    };
+    assert!(row("acme").contains("accessible"));
+    assert!(row("initech").contains("SAML SSO authorization required"));
+    assert!(row("initech").contains("Authorize</a>"));
+    assert!(!row("acme").contains("Authorize</a>"));
+    assert!(html.contains("4321 of 5000"));
+    assert!(html.contains("<li>octocat/locked</li>"));
+    assert!(!html.contains("<li>octocat/website</li>"));
+}
+
+#[tokio::test]
+async fn allowlist_refuses_sign_in_and_bearer_tokens() {
+    let github = StubGitHub::start("octocat", repos()).await;
+    let mut config = common::config();
+    config.allowed_users = vec!["hubot".to_string()];
+    let app = common::spawn_app_against_with(config, github.url()).await;
+
+    let (state, cookie) = begin_login(&app).await;
+    let callback = client()
+        .get(format!("{app}/auth/callback"))
+        .query(&[("code", GOOD_CODE), ("state", state.as_str())])
+        .header(header::COOKIE, cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(callback.status(), StatusCode::FORBIDDEN);
+    let dashboard = client()
+        .get(format!("{app}/dashboard"))
+        .header(header::COOKIE, cookies(&callback))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(dashboard.status(), StatusCode::SEE_OTHER);
+
+    let bearer = client()
+        .get(format!("{app}/api/v1/repos"))
+        .bearer_auth(STUB_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bearer.status(), StatusCode::FORBIDDEN);
+    assert_eq!(github.hits("GET /user/repos"), 0);
+}
@@ -0,0 +1,133 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json, Router,
+    extract::{Path as UrlPath, State},
+    http::HeaderMap,
+    routing::post,
+};
+use chrono::{TimeDelta, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use learning_rust::{config::GitHubAppConfig, github::GitHubClient, github_app::GitHubAppAuth};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use url::Url;
+
+const APP_ID: u64 = 4242;
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name),
+    )
+    .unwrap()
+}
+
+struct StubState {
+    /// The JWT sent with each token request, in order.
+    jwts: Mutex<Vec<String>>,
+    /// Lifetime of every token issued.
+    ttl: TimeDelta,
+}
+
+async fn access_tokens(
+    State(state): State<Arc<StubState>>,
+    UrlPath(id): UrlPath<u64>,
+    headers: HeaderMap,
+) -> Json<Value> {
+    let jwt = headers["authorization"]
+        .to_str()
+        .unwrap()
+        .trim_start_matches("Bearer ")
+        .to_string();
+    let mut jwts = state.jwts.lock().unwrap();
+    jwts.push(jwt);
+    Json(json!({
+        "token": format!("ghs_{id}_{}", jwts.len()),
+        "expires_at": (Utc::now() + state.ttl).to_rfc3339(),
+    }))
+}
+
+async fn start(ttl: TimeDelta) -> (GitHubClient, Arc<StubState>) {
+    let state = Arc::new(StubState {
+        jwts: Mutex::default(),
+        ttl,
+    });
+    let router = Router::new()
+        .route("/app/installations/{id}/access_tokens", post(access_tokens))
+        .with_state(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    (
+        GitHubClient::new(&Url::parse(&url).unwrap()).unwrap(),
+        state,
+    )
+}
+
+fn app() -> GitHubAppAuth {
+    GitHubAppAuth::new(&GitHubAppConfig {
+        app_id: APP_ID,
+        private_key_pem: fixture("app-key.pem"),
+    })
+    .unwrap()
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    iat: i64,
+    exp: i64,
+}
+
+#[test]
+fn app_jwt_is_signed_with_the_app_key() {
+    let jwt = app().app_jwt().unwrap();
+
+    let key = DecodingKey::from_rsa_pem(fixture("app-key.pub.pem").as_bytes()).unwrap();
+    let claims = jsonwebtoken::decode::<Claims>(&jwt, &key, &Validation::new(Algorithm::RS256))
+        .unwrap()
+        .claims;
+    assert_eq!(claims.iss, APP_ID.to_string());
+    let now = Utc::now().timestamp();
+    assert!(claims.iat < now);
+    assert!(claims.exp - claims.iat <= 10 * 60);
+}
+
+#[tokio::test]
+async fn cached_installation_token_is_reused() {
+    let (github, stub) = start(TimeDelta::hours(1)).await;
+    let app = app();
+
+    let first = app.installation_token(&github, 7).await.unwrap();
+    let second = app.installation_token(&github, 7).await.unwrap();
+    let other = app.installation_token(&github, 8).await.unwrap();
+
+    assert_eq!(first, "ghs_7_1");
+    assert_eq!(second, first);
+    assert_eq!(other, "ghs_8_2");
+    assert_eq!(stub.jwts.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn expiring_installation_token_is_refreshed() {
+    // Inside the refresh margin from the moment it is issued.
+    let (github, stub) = start(TimeDelta::minutes(2)).await;
+    let app = app();
+
+    let first = app.installation_token(&github, 7).await.unwrap();
+    let second = app.installation_token(&github, 7).await.unwrap();
+
+    assert_eq!(first, "ghs_7_1");
+    assert_eq!(second, "ghs_7_2");
+    let jwts = stub.jwts.lock().unwrap();
+    assert_eq!(jwts.len(), 2);
+    assert!(jwts.iter().all(|jwt| jwt.split('.').count() == 3));
+}
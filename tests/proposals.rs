@@ -0,0 +1,229 @@
+mod common;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use learning_rust::{
+    AppState,
+    audit::AuditTrail,
+    error::AppError,
+    github_fake::FakeGitHub,
+    models::{RemoveItem, SessionData},
+    proposals::{ProposalStatus, Proposals},
+    service::{AuditService, RemovalPolicy},
+    workspaces::{Role, Workspace},
+};
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+
+fn item(repo: &str, username: &str) -> RemoveItem {
+    RemoveItem {
+        repo: repo.to_string(),
+        username: username.to_string(),
+    }
+}
+
+/// The shared fixture, plus `hubot` as a second admin on `website`.
+fn github() -> Arc<FakeGitHub> {
+    let mut fixture = common::fixture();
+    let website = fixture
+        .repos
+        .iter_mut()
+        .find(|r| r.repo.name == "website")
+        .unwrap();
+    website.collaborators.push(
+        serde_json::from_value(json!({
+            "login": "hubot",
+            "id": 9,
+            "permissions": {"admin": true},
+            "role_name": "admin"
+        }))
+        .unwrap(),
+    );
+    Arc::new(FakeGitHub::new(fixture))
+}
+
+fn service(github: Arc<FakeGitHub>) -> AuditService {
+    AuditService::new(github, AuditTrail::default()).with_policy(RemovalPolicy {
+        approval_required_repos: vec!["website".to_string()],
+        ..RemovalPolicy::default()
+    })
+}
+
+fn as_user(login: &str) -> SessionData {
+    SessionData {
+        user_login: login.to_string(),
+        ..common::session()
+    }
+}
+
+#[tokio::test]
+async fn approval_required_repos_only_change_through_an_approved_proposal() {
+    let github = github();
+    let service = service(github.clone());
+    let items = vec![item("website", "alice")];
+
+    let direct = service
+        .remove(&common::session(), items.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        direct.failed[0].reason,
+        "removal requires an approved proposal"
+    );
+
+    let approver = as_user("hubot");
+    service
+        .check_approver(&approver, common::VIEWER, common::VIEWER, &items)
+        .await
+        .unwrap();
+    let approved = service
+        .remove_approved(
+            &common::session(),
+            common::VIEWER,
+            &approver.user_login,
+            items,
+        )
+        .await
+        .unwrap();
+    assert_eq!(approved.success.len(), 1);
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "bob", "hubot"]
+    );
+}
+
+#[tokio::test]
+async fn approver_must_be_another_admin() {
+    let service = service(github());
+    let items = vec![item("website", "bob")];
+
+    for approver in ["octocat", "alice", "stranger"] {
+        let result = service
+            .check_approver(&as_user(approver), common::VIEWER, common::VIEWER, &items)
+            .await;
+        assert!(
+            matches!(result, Err(AppError::Forbidden)),
+            "{approver} must not approve"
+        );
+    }
+}
+
+#[tokio::test]
+async fn proposals_are_decided_once_and_hide_the_proposer_token() {
+    let proposals = Proposals::default();
+    let proposal = proposals.submit(
+        common::session(),
+        None,
+        &platform(),
+        vec![item("infra", "mallory")],
+    );
+
+    let body = serde_json::to_string(&proposal).unwrap();
+    assert!(!body.contains(common::TOKEN));
+    assert_eq!(proposals.pending("platform").len(), 1);
+
+    let rejected = proposals
+        .decide(&proposal.id, "hubot", ProposalStatus::Rejected)
+        .unwrap();
+    assert_eq!(rejected.decided_by.as_deref(), Some("hubot"));
+    assert!(proposals.pending("platform").is_empty());
+
+    let again = proposals.decide(&proposal.id, "hubot", ProposalStatus::Approved);
+    assert!(matches!(again, Err(AppError::Conflict(_))));
+}
+
+/// `github()`, plus `hubot/tools` where the viewer is an admin collaborator and
+/// `octo-org/infra` where the viewer is an admin and `mallory` can write.
+fn shared_github() -> Arc<FakeGitHub> {
+    let mut fixture = common::fixture();
+    for repo in [
+        json!({
+            "id": 10,
+            "name": "tools",
+            "owner": {"login": "hubot"},
+            "private": true,
+            "archived": false,
+            "fork": false,
+            "collaborators": [
+                {"login": "octocat", "id": 1, "permissions": {"admin": true}, "role_name": "admin"}
+            ]
+        }),
+        json!({
+            "id": 11,
+            "name": "infra",
+            "owner": {"login": "octo-org"},
+            "private": true,
+            "archived": false,
+            "fork": false,
+            "collaborators": [
+                {"login": "octocat", "id": 1, "permissions": {"admin": true}, "role_name": "admin"},
+                {"login": "mallory", "id": 6, "permissions": {"push": true}, "role_name": "write"}
+            ]
+        }),
+    ] {
+        fixture.repos.push(serde_json::from_value(repo).unwrap());
+    }
+    Arc::new(FakeGitHub::new(fixture))
+}
+
+fn platform() -> Workspace {
+    Workspace {
+        name: "platform".to_string(),
+        org: "octo-org".to_string(),
+        members: BTreeMap::from([
+            ("octocat".to_string(), Role::Approver),
+            ("hubot".to_string(), Role::Remover),
+        ]),
+    }
+}
+
+/// App state acting as the viewer for every bearer token, with `platform`
+/// configured, for tests that seed proposals from other users.
+async fn state(github: Arc<FakeGitHub>) -> AppState {
+    let mut config = common::config();
+    config.workspaces = vec![platform()];
+    AppState::with_github(config, github).await.unwrap()
+}
+
+fn get(url: String) -> reqwest::RequestBuilder {
+    Client::new().get(url).bearer_auth(common::TOKEN)
+}
+
+#[tokio::test]
+async fn approval_fails_once_the_proposer_signed_out() {
+    let github = shared_github();
+    let state = state(github.clone()).await;
+    let session_id = state.sessions.create(as_user("hubot")).unwrap();
+    let proposal = state.proposals.submit(
+        as_user("hubot"),
+        Some(session_id.clone()),
+        &platform(),
+        vec![item("infra", "mallory")],
+    );
+    state.sessions.revoke(&session_id).unwrap();
+    let base = common::serve(state).await;
+
+    let response = Client::new()
+        .post(format!(
+            "{base}/workspaces/platform/proposals/{}/approve",
+            proposal.id
+        ))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(
+        github.collaborator_logins("octo-org", "infra"),
+        ["octocat", "mallory"]
+    );
+    let pending: Vec<Value> = get(format!("{base}/workspaces/platform/proposals"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+}
@@ -0,0 +1,63 @@
+use learning_rust::redact::{redact, register_secret};
+
+#[test]
+fn redacts_github_tokens() {
+    assert_eq!(
+        redact("token=ghp_abcDEF123 used for gho_xyz_789"),
+        "token=[REDACTED] used for [REDACTED]"
+    );
+    assert_eq!(
+        redact("pat github_pat_11AB_cdEF rejected"),
+        "pat [REDACTED] rejected"
+    );
+}
+
+#[test]
+fn redacts_authorization_headers() {
+    assert_eq!(
+        redact(r#"authorization: "Bearer abc.def-123""#),
+        r#"authorization: "Bearer [REDACTED]""#
+    );
+}
+
+#[test]
+fn redacts_secret_keys_in_text_and_json() {
+    assert_eq!(
+        redact("GET /auth/callback?code=4f2a9c&state=xyz"),
+        "GET /auth/callback?code=[REDACTED]&state=xyz"
+    );
+    assert_eq!(
+        redact(r#"{"client_secret":"s3cr3t","access_token": "tok"}"#),
+        r#"{"client_secret":"[REDACTED]","access_token": "[REDACTED]"}"#
+    );
+    assert_eq!(
+        redact(r#"{"message":"exchange failed client_secret=\"abc\""}"#),
+        r#"{"message":"exchange failed client_secret=\"[REDACTED]\""}"#
+    );
+}
+
+#[test]
+fn leaves_similar_field_names_alone() {
+    let line = "github_request http.response.status_code=404 error_code=bad_verification_code";
+    assert_eq!(redact(line), line);
+}
+
+#[test]
+fn leaves_code_and_token_in_prose_alone() {
+    let line = "github rejected the request, status code: 404; token: missing";
+    assert_eq!(redact(line), line);
+    assert_eq!(
+        redact(r#"{"code": "4f2a9c", "token":"abc"}"#),
+        r#"{"code": "[REDACTED]", "token":"[REDACTED]"}"#
+    );
+}
+
+#[test]
+fn redacts_registered_secrets() {
+    register_secret("correct-horse-battery");
+    register_secret("short");
+    assert_eq!(
+        redact("session secret correct-horse-battery is short"),
+        "session secret [REDACTED] is short"
+    );
+}
@@ -0,0 +1,482 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{Json, Router, extract::State, routing::post};
+use learning_rust::{
+    audit::AuditTrail,
+    error::AppError,
+    github_fake::FakeGitHub,
+    models::{DeployKeyItem, FailureKind, RemoveItem, SessionData},
+    notify::Notifier,
+    service::{AuditService, ItemOutcome, RemovalPolicy},
+    utils,
+};
+use serde_json::{Value, json};
+use tokio::{net::TcpListener, sync::mpsc};
+use url::Url;
+
+fn item(repo: &str, username: &str) -> RemoveItem {
+    RemoveItem {
+        repo: repo.to_string(),
+        username: username.to_string(),
+    }
+}
+
+fn reason_for<'a>(
+    response: &'a learning_rust::models::RemoveResponse,
+    repo: &str,
+    username: &str,
+) -> &'a str {
+    response
+        .failed
+        .iter()
+        .find(|f| f.repo == repo && f.username == username)
+        .map(|f| f.reason.as_str())
+        .unwrap_or_else(|| panic!("no failure for {repo}/{username}"))
+}
+
+#[tokio::test]
+async fn removes_collaborator_from_owned_repo() {
+    let github = common::fake();
+    let service = AuditService::new(github.clone(), AuditTrail::default());
+
+    let response = service
+        .remove(&common::session(), vec![item("website", "alice")])
+        .await
+        .unwrap();
+
+    assert_eq!(response.success.len(), 1);
+    assert!(response.failed.is_empty());
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "bob"]
+    );
+}
+
+#[tokio::test]
+async fn rejects_empty_batch() {
+    let service = AuditService::new(common::fake(), AuditTrail::default());
+
+    let result = service.remove(&common::session(), Vec::new()).await;
+
+    assert!(matches!(result, Err(AppError::BadRequest(_))));
+}
+
+#[tokio::test]
+async fn reports_each_failure_reason() {
+    let github = common::fake();
+    let service = AuditService::new(github.clone(), AuditTrail::default())
+        .with_mutation_interval(Duration::ZERO);
+
+    let response = service
+        .remove(
+            &common::session(),
+            vec![
+                item(" ", "alice"),
+                item("website", "octocat"),
+                item("someone-elses", "octocat"),
+                item("missing", "alice"),
+                item("locked", "alice"),
+                item("spam-guarded", "dave"),
+                item("website", "nobody"),
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert!(response.success.is_empty());
+    assert_eq!(response.failed.len(), 7);
+    assert_eq!(
+        reason_for(&response, " ", "alice"),
+        "repo and username must be non-empty"
+    );
+    assert_eq!(
+        reason_for(&response, "website", "octocat"),
+        "cannot remove authenticated user"
+    );
+    assert_eq!(
+        reason_for(&response, "missing", "alice"),
+        "repository is not owned by authenticated user"
+    );
+    assert_eq!(
+        reason_for(&response, "locked", "alice"),
+        "insufficient permissions"
+    );
+    assert_eq!(
+        reason_for(&response, "spam-guarded", "dave"),
+        "validation failed or abuse detection triggered"
+    );
+    assert_eq!(
+        reason_for(&response, "website", "nobody"),
+        "collaborator not found"
+    );
+    let labels = response
+        .failed
+        .iter()
+        .map(|f| f.kind.label())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        labels,
+        [
+            "invalid",
+            "self",
+            "self",
+            "not_owner",
+            "forbidden",
+            "unprocessable",
+            "not_found"
+        ]
+    );
+    assert_eq!(
+        github.collaborator_logins("octocat", "spam-guarded"),
+        ["dave"]
+    );
+}
+
+#[tokio::test]
+async fn spaces_deletes_and_keeps_request_order_across_repos() {
+    let github = common::fake();
+    let service = AuditService::new(github.clone(), AuditTrail::default())
+        .with_concurrency(2)
+        .with_mutation_interval(Duration::from_millis(100));
+    let settled = Mutex::new(Vec::new());
+
+    let started = Instant::now();
+    let response = service
+        .remove_with_progress(
+            &common::session(),
+            vec![
+                item("website", "alice"),
+                item("old-fork", "carol"),
+                item("website", "bob"),
+            ],
+            |outcome| {
+                if let ItemOutcome::Removed(removed) = outcome {
+                    settled.lock().unwrap().push(removed.username.clone());
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    let removed: Vec<_> = response
+        .success
+        .iter()
+        .map(|s| s.username.as_str())
+        .collect();
+    assert_eq!(removed, ["alice", "carol", "bob"]);
+    let settled = settled.into_inner().unwrap();
+    let position = |login: &str| settled.iter().position(|s| s == login).unwrap();
+    assert!(position("alice") < position("bob"));
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat"]
+    );
+}
+
+#[tokio::test]
+async fn paces_deletes_per_user() {
+    let service = AuditService::new(common::fake(), AuditTrail::default())
+        .with_mutation_interval(Duration::from_millis(300));
+    let hubot = SessionData {
+        user_login: "hubot".to_string(),
+        ..common::session()
+    };
+    let remove = |session: SessionData, username: &'static str| {
+        let service = service.clone();
+        async move {
+            service
+                .remove_outside_collaborators(&session, "acme", vec![username.to_string()])
+                .await
+                .unwrap()
+        }
+    };
+
+    // A second user does not wait for the first.
+    let started = Instant::now();
+    tokio::join!(remove(common::session(), "alice"), remove(hubot, "bob"));
+    assert!(started.elapsed() < Duration::from_millis(250));
+
+    // The same user does.
+    let monalisa = SessionData {
+        user_login: "monalisa".to_string(),
+        ..common::session()
+    };
+    let started = Instant::now();
+    tokio::join!(remove(monalisa.clone(), "carol"), remove(monalisa, "dave"));
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn protected_collaborator_is_never_removed() {
+    let github = common::fake();
+    let service =
+        AuditService::new(github.clone(), AuditTrail::default()).with_policy(RemovalPolicy {
+            protected_collaborators: vec!["Alice".to_string()],
+            ..RemovalPolicy::default()
+        });
+
+    let response = service
+        .remove(
+            &common::session(),
+            vec![item("website", "alice"), item("website", "bob")],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        reason_for(&response, "website", "alice"),
+        "collaborator is protected by policy"
+    );
+    assert_eq!(response.success.len(), 1);
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice"]
+    );
+}
+
+#[tokio::test]
+async fn rejects_batch_over_max_size() {
+    let github = common::fake();
+    let service =
+        AuditService::new(github.clone(), AuditTrail::default()).with_policy(RemovalPolicy {
+            max_batch_size: Some(1),
+            ..RemovalPolicy::default()
+        });
+
+    let result = service
+        .remove(
+            &common::session(),
+            vec![item("website", "alice"), item("website", "bob")],
+        )
+        .await;
+
+    match result {
+        Err(AppError::BadRequest(message)) => {
+            assert_eq!(message, "items must not contain more than 1 entries")
+        }
+        other => panic!("expected a bad request, got {other:?}"),
+    }
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice", "bob"]
+    );
+}
+
+/// Serves a webhook endpoint that forwards every payload it receives.
+async fn webhook_receiver() -> (Url, mpsc::UnboundedReceiver<Value>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let router =
+        Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(sender): State<mpsc::UnboundedSender<Value>>,
+                     Json(payload): Json<Value>| async move {
+                        sender.send(payload).ok();
+                    },
+                ),
+            )
+            .with_state(sender);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/hook", listener.local_addr().unwrap())).unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    (url, receiver)
+}
+
+#[tokio::test]
+async fn webhook_receives_batch_summary() {
+    let (url, mut payloads) = webhook_receiver().await;
+    let service = AuditService::new(common::fake(), AuditTrail::default())
+        .with_notifier(Notifier::new(vec![url]));
+
+    service
+        .remove(
+            &common::session(),
+            vec![item("website", "alice"), item("website", "nobody")],
+        )
+        .await
+        .unwrap();
+
+    let payload = tokio::time::timeout(Duration::from_secs(5), payloads.recv())
+        .await
+        .expect("webhook called")
+        .unwrap();
+    assert_eq!(
+        payload,
+        json!({
+            "event": "collaborator_remove",
+            "actor": "octocat",
+            "success": [{ "repo": "website", "username": "alice" }],
+            "failed": [{
+                "repo": "website",
+                "username": "nobody",
+                "reason": "collaborator not found",
+            }],
+        })
+    );
+}
+
+#[tokio::test]
+async fn deploy_key_removal_skips_approval_and_notifies_webhooks() {
+    let (url, mut payloads) = webhook_receiver().await;
+    let github = common::fake();
+    let service = AuditService::new(github.clone(), AuditTrail::default())
+        .with_policy(RemovalPolicy {
+            approval_required_repos: vec!["*".to_string()],
+            ..RemovalPolicy::default()
+        })
+        .with_notifier(Notifier::new(vec![url]));
+
+    let response = service
+        .remove_deploy_keys(
+            &common::session(),
+            vec![DeployKeyItem {
+                repo: "website".to_string(),
+                key_id: 12,
+            }],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.success.len(), 1);
+    assert_eq!(github.deploy_key_ids("octocat", "website"), [11]);
+    let payload = tokio::time::timeout(Duration::from_secs(5), payloads.recv())
+        .await
+        .expect("webhook called")
+        .unwrap();
+    assert_eq!(
+        payload,
+        json!({
+            "event": "deploy_key_remove",
+            "actor": "octocat",
+            "success": [{ "repo": "website", "key_id": 12 }],
+            "failed": [],
+        })
+    );
+}
+
+#[tokio::test]
+async fn self_removal_is_refused_whatever_the_case() {
+    let github = common::fake();
+    let service = AuditService::new(github.clone(), AuditTrail::default());
+
+    let response = service
+        .remove(&common::session(), vec![item("website", "OctoCat")])
+        .await
+        .unwrap();
+
+    let reason = reason_for(&response, "website", "OctoCat");
+    assert_eq!(reason, "cannot remove authenticated user");
+    assert_eq!(response.failed[0].kind, FailureKind::SelfRemoval);
+    assert_eq!(response.failed[0].kind.label(), "self");
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice", "bob"]
+    );
+}
+
+#[tokio::test]
+async fn repo_names_that_leave_their_path_segment_are_refused() {
+    let github = common::fake();
+    let service =
+        AuditService::new(github.clone(), AuditTrail::default()).with_policy(RemovalPolicy {
+            approval_required_repos: vec!["website".to_string()],
+            ..RemovalPolicy::default()
+        });
+
+    let response = service
+        .remove(
+            &common::session(),
+            vec![item("x/../website", "alice"), item(".website", "alice")],
+        )
+        .await
+        .unwrap();
+
+    assert!(response.success.is_empty());
+    for repo in ["x/../website", ".website"] {
+        assert_eq!(
+            reason_for(&response, repo, "alice"),
+            "invalid repository name"
+        );
+    }
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice", "bob"]
+    );
+}
+
+#[tokio::test]
+async fn usernames_that_leave_their_path_segment_are_refused() {
+    let github = common::fake();
+    let service =
+        AuditService::new(github.clone(), AuditTrail::default()).with_policy(RemovalPolicy {
+            protected_collaborators: vec!["alice".to_string()],
+            ..RemovalPolicy::default()
+        });
+
+    let response = service
+        .remove(
+            &common::session(),
+            vec![
+                item("website", "x/../alice"),
+                item("website", "x/../octocat"),
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert!(response.success.is_empty());
+    for username in ["x/../alice", "x/../octocat"] {
+        assert_eq!(
+            reason_for(&response, "website", username),
+            "invalid username"
+        );
+    }
+    assert_eq!(
+        github.collaborator_logins("octocat", "website"),
+        ["octocat", "alice", "bob"]
+    );
+}
+
+#[tokio::test]
+async fn failed_team_removal_is_audited() {
+    let mut fixture = common::fixture();
+    fixture.token = Some("another-token".to_string());
+    fixture.teams.insert(
+        "octo-org".to_string(),
+        serde_json::from_value(json!([{ "slug": "sre", "name": "SRE", "members": ["hubot"] }]))
+            .unwrap(),
+    );
+    let audit_log = std::env::temp_dir().join(format!("audit-{}.jsonl", utils::random_token(12)));
+    let service = AuditService::new(
+        Arc::new(FakeGitHub::new(fixture)),
+        AuditTrail::open(Some(&audit_log)).unwrap(),
+    );
+
+    let result = service
+        .remove_team_member(&common::session(), "octo-org", "sre", "hubot")
+        .await;
+
+    assert!(matches!(result, Err(AppError::Auth)));
+    let events: Vec<Value> = std::fs::read_to_string(&audit_log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["action"], "team_member_remove");
+    assert_eq!(events[0]["outcome"], "failure");
+    assert_eq!(
+        events[0]["detail"],
+        "octo-org/sre: hubot: authentication failed"
+    );
+    std::fs::remove_file(audit_log).ok();
+}
@@ -0,0 +1,173 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use axum_extra::extract::cookie::Key;
+use chrono::Utc;
+use learning_rust::{
+    models::SessionData,
+    session::{InMemorySessionStore, SessionRecord, SessionStore, Sessions, SqliteSessionStore},
+    utils,
+};
+
+const IDLE: Duration = Duration::from_secs(60 * 60);
+const ABSOLUTE: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn session(login: &str) -> SessionData {
+    SessionData {
+        access_token: format!("gho_{login}_secret"),
+        user_login: login.to_string(),
+        csrf_token: "csrf".to_string(),
+        granted_scopes: Some("repo, read:org".to_string()),
+    }
+}
+
+fn sessions(store: Arc<dyn SessionStore>) -> Sessions {
+    Sessions::new(store, IDLE, ABSOLUTE)
+}
+
+/// A record last seen `idle` ago and created `age` ago.
+fn record(login: &str, age: Duration, idle: Duration) -> SessionRecord {
+    let now = Utc::now().timestamp();
+    SessionRecord {
+        session: session(login),
+        created_at: now - age.as_secs() as i64,
+        last_seen_at: now - idle.as_secs() as i64,
+    }
+}
+
+fn temp_db() -> PathBuf {
+    std::env::temp_dir().join(format!("sessions-{}.db", utils::random_token(12)))
+}
+
+#[test]
+fn idle_sessions_expire() {
+    let store = Arc::new(InMemorySessionStore::default());
+    let sessions = sessions(store.clone());
+    let minute = Duration::from_secs(60);
+    store
+        .insert("fresh", &record("alice", minute, minute))
+        .unwrap();
+    store
+        .insert("idle", &record("alice", IDLE * 2, IDLE + minute))
+        .unwrap();
+
+    assert!(sessions.load("fresh").unwrap().is_some());
+    assert!(sessions.load("idle").unwrap().is_none());
+    // Deleted on sight, not just hidden.
+    assert!(store.get("idle").unwrap().is_none());
+}
+
+#[test]
+fn sessions_expire_after_absolute_timeout_despite_activity() {
+    let store = Arc::new(InMemorySessionStore::default());
+    let sessions = sessions(store.clone());
+    store
+        .insert("old", &record("alice", ABSOLUTE * 2, Duration::ZERO))
+        .unwrap();
+
+    assert!(sessions.load("old").unwrap().is_none());
+}
+
+#[test]
+fn purge_removes_only_expired_sessions() {
+    let store = Arc::new(InMemorySessionStore::default());
+    let sessions = sessions(store.clone());
+    let id = sessions.create(session("alice")).unwrap();
+    store
+        .insert("idle", &record("bob", IDLE * 2, IDLE * 2))
+        .unwrap();
+
+    assert_eq!(sessions.purge_expired().unwrap(), 1);
+    assert!(sessions.load(&id).unwrap().is_some());
+}
+
+#[test]
+fn revoke_user_ends_every_session_of_that_user() {
+    let sessions = sessions(Arc::new(InMemorySessionStore::default()));
+    let first = sessions.create(session("alice")).unwrap();
+    let second = sessions.create(session("alice")).unwrap();
+    let other = sessions.create(session("bob")).unwrap();
+
+    assert_eq!(sessions.revoke_user("alice").unwrap(), 2);
+
+    assert!(sessions.load(&first).unwrap().is_none());
+    assert!(sessions.load(&second).unwrap().is_none());
+    assert_eq!(sessions.load(&other).unwrap().unwrap().user_login, "bob");
+}
+
+#[test]
+fn sqlite_store_expires_and_revokes_like_memory() {
+    let path = temp_db();
+    let key = Key::generate();
+    let store = Arc::new(SqliteSessionStore::open(&path, &key).unwrap());
+    let sessions = sessions(store.clone());
+    let live = sessions.create(session("alice")).unwrap();
+    let other = sessions.create(session("alice")).unwrap();
+    store
+        .insert("idle", &record("alice", IDLE * 2, IDLE * 2))
+        .unwrap();
+
+    assert!(sessions.load("idle").unwrap().is_none());
+    assert_eq!(
+        sessions.load(&live).unwrap().unwrap().access_token,
+        "gho_alice_secret"
+    );
+    assert_eq!(sessions.revoke_user("alice").unwrap(), 2);
+    assert!(sessions.load(&other).unwrap().is_none());
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn sqlite_store_encrypts_session_data() {
+    let path = temp_db();
+    let key = Key::generate();
+    let id = sessions(Arc::new(SqliteSessionStore::open(&path, &key).unwrap()))
+        .create(session("alice"))
+        .unwrap();
+
+    let data: String = rusqlite::Connection::open(&path)
+        .unwrap()
+        .query_row("SELECT data FROM sessions WHERE id = ?1", [&id], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert!(!data.contains("gho_alice_secret"));
+    assert!(!data.contains("alice"));
+
+    // Readable with the same key after a restart, but not with another.
+    let reopened = sessions(Arc::new(SqliteSessionStore::open(&path, &key).unwrap()));
+    assert!(reopened.load(&id).unwrap().is_some());
+    let other_key = sessions(Arc::new(
+        SqliteSessionStore::open(&path, &Key::generate()).unwrap(),
+    ));
+    assert!(other_key.load(&id).unwrap().is_none());
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn is_live_checks_without_refreshing() {
+    let store = Arc::new(InMemorySessionStore::default());
+    let sessions = sessions(store.clone());
+    let live = sessions.create(session("alice")).unwrap();
+    store
+        .insert(
+            "idle",
+            &record("alice", IDLE * 2, IDLE + Duration::from_secs(60)),
+        )
+        .unwrap();
+    store
+        .insert("quiet", &record("alice", IDLE, IDLE / 2))
+        .unwrap();
+    let revoked = sessions.create(session("alice")).unwrap();
+    sessions.revoke(&revoked).unwrap();
+
+    assert!(sessions.is_live(&live, "alice").unwrap());
+    assert!(!sessions.is_live(&live, "bob").unwrap());
+    assert!(!sessions.is_live("idle", "alice").unwrap());
+    assert!(!sessions.is_live(&revoked, "alice").unwrap());
+    assert!(sessions.is_live("quiet", "alice").unwrap());
+    // Still as quiet as before the checks.
+    let last_seen_at = store.get("quiet").unwrap().unwrap().last_seen_at;
+    assert!(Utc::now().timestamp() - last_seen_at >= (IDLE / 2).as_secs() as i64);
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::stub::{STUB_TOKEN, StubGitHub, StubRepo};
+use learning_rust::{
+    AppState,
+    audit::AuditTrail,
+    github::GitHubClient,
+    models::{RemoveItem, SessionData},
+    serve,
+    service::{AuditService, UNPROCESSED_REASON},
+    shutdown::Shutdown,
+};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use url::Url;
+
+fn item(username: &str) -> RemoveItem {
+    RemoveItem {
+        repo: "website".to_string(),
+        username: username.to_string(),
+    }
+}
+
+async fn service(delete_delay: Duration, shutdown: &Shutdown) -> (StubGitHub, AuditService) {
+    let github = StubGitHub::start(
+        "octocat",
+        vec![StubRepo {
+            delete_delay: Some(delete_delay),
+            ..StubRepo::new("website", &["alice", "bob", "carol"])
+        }],
+    )
+    .await;
+    let client = GitHubClient::new(&Url::parse(github.url()).unwrap()).unwrap();
+    let service = AuditService::new(Arc::new(client), AuditTrail::default())
+        .with_shutdown(shutdown.clone())
+        .with_mutation_interval(Duration::ZERO);
+    (github, service)
+}
+
+fn session() -> SessionData {
+    SessionData {
+        access_token: STUB_TOKEN.to_string(),
+        ..common::session()
+    }
+}
+
+#[tokio::test]
+async fn drain_waits_for_batches_that_finish_in_time() {
+    let shutdown = Shutdown::new(Duration::from_secs(5));
+    let (github, service) = service(Duration::from_millis(50), &shutdown).await;
+
+    let batch = tokio::spawn(async move {
+        service
+            .remove(&session(), vec![item("alice"), item("bob")])
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    shutdown.request();
+    shutdown.drain().await;
+
+    let response = batch.await.unwrap().unwrap();
+    assert_eq!(response.success.len(), 2);
+    assert_eq!(github.collaborators("website"), ["carol"]);
+}
+
+#[tokio::test]
+async fn items_past_the_drain_deadline_are_reported_unprocessed() {
+    let shutdown = Shutdown::new(Duration::from_millis(100));
+    let (github, service) = service(Duration::from_millis(300), &shutdown).await;
+
+    let batch = tokio::spawn(async move {
+        service
+            .remove(&session(), vec![item("alice"), item("bob"), item("carol")])
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.request();
+    shutdown.drain().await;
+
+    let response = batch.await.unwrap().unwrap();
+    // The DELETE already in flight completes; nothing after it is attempted.
+    assert_eq!(response.success.len(), 1);
+    assert_eq!(response.success[0].username, "alice");
+    assert_eq!(response.failed.len(), 2);
+    assert!(
+        response
+            .failed
+            .iter()
+            .all(|f| f.reason == UNPROCESSED_REASON)
+    );
+    assert_eq!(github.collaborators("website"), ["bob", "carol"]);
+}
+
+#[tokio::test]
+async fn serve_drains_a_running_job_before_returning() {
+    let github = StubGitHub::start(
+        "octocat",
+        vec![StubRepo {
+            delete_delay: Some(Duration::from_millis(150)),
+            ..StubRepo::new("website", &["alice", "bob", "carol"])
+        }],
+    )
+    .await;
+    let audit_log = std::env::temp_dir().join(format!(
+        "audit-{}.jsonl",
+        learning_rust::utils::random_token(12)
+    ));
+    let mut config = common::config();
+    config.github_api_url = Url::parse(github.url()).unwrap();
+    config.github_token = Some(STUB_TOKEN.to_string());
+    config.audit_log_path = Some(audit_log.clone());
+    let state = AppState::from_config(config).await.unwrap();
+    let shutdown = state.shutdown.clone();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(serve(listener, state));
+
+    let accepted: Value = reqwest::Client::new()
+        .post(format!("{base}/api/v1/remove"))
+        .bearer_auth(STUB_TOKEN)
+        .json(&json!({
+            "items": [
+                { "repo": "website", "username": "alice" },
+                { "repo": "website", "username": "bob" },
+            ],
+            "background": true,
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(accepted["job_id"].is_string());
+
+    // No connection is left open, so only the drain holds `serve` back.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.request();
+    server.await.unwrap().unwrap();
+
+    assert_eq!(github.collaborators("website"), ["carol"]);
+    let removed: Vec<Value> = std::fs::read_to_string(&audit_log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .filter(|event| event["action"] == "collaborator_remove" && event["outcome"] == "success")
+        .collect();
+    assert_eq!(removed.len(), 2);
+    std::fs::remove_file(audit_log).ok();
+}
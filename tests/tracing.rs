@@ -0,0 +1,29 @@
+mod common;
+
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing_subscriber::layer::SubscriberExt;
+
+#[tokio::test]
+async fn error_responses_carry_trace_id() {
+    let provider = SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let base = common::spawn_app(common::fake()).await;
+    let response = reqwest::Client::new()
+        .get(format!("{base}/api/v1/repos"))
+        .bearer_auth("wrong-token")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = response.json().await.unwrap();
+    let trace_id = body["trace_id"].as_str().expect("trace_id present");
+    assert_eq!(trace_id.len(), 32);
+    assert_ne!(trace_id, "0".repeat(32));
+}
@@ -0,0 +1,433 @@
+mod common;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use learning_rust::{
+    config::GitHubAppConfig,
+    github_fake::FakeGitHub,
+    workspaces::{Role, Workspace},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+/// The shared fixture, plus `infra` owned by `octo-org`, where the viewer is
+/// an admin and outside collaborator `mallory` can write. `octo-org` has an
+/// `engineering` team with a nested `sre` team that maintains `infra`.
+fn github() -> Arc<FakeGitHub> {
+    let mut fixture = common::fixture();
+    fixture.repos.push(
+        serde_json::from_value(json!({
+            "id": 10,
+            "name": "infra",
+            "owner": {"login": "octo-org"},
+            "private": true,
+            "archived": false,
+            "fork": false,
+            "collaborators": [
+                {"login": "octocat", "id": 1, "permissions": {"admin": true}, "role_name": "admin"},
+                {"login": "mallory", "id": 6, "permissions": {"push": true}, "role_name": "write"}
+            ]
+        }))
+        .unwrap(),
+    );
+    fixture
+        .outside_collaborators
+        .insert("octo-org".to_string(), vec!["mallory".to_string()]);
+    fixture.teams.insert(
+        "octo-org".to_string(),
+        serde_json::from_value(json!([
+            {
+                "slug": "sre",
+                "name": "SRE",
+                "parent": "engineering",
+                "maintainers": ["octocat"],
+                "members": ["hubot"],
+                "repos": [{"name": "infra", "permissions": {"maintain": true, "push": true}}]
+            },
+            {
+                "slug": "engineering",
+                "name": "Engineering",
+                "members": ["octocat", "hubot"]
+            }
+        ]))
+        .unwrap(),
+    );
+    Arc::new(FakeGitHub::new(fixture))
+}
+
+async fn spawn(github: Arc<FakeGitHub>, members: &[(&str, Role)]) -> String {
+    let mut config = common::config();
+    config.workspaces = vec![Workspace {
+        name: "platform".to_string(),
+        org: "octo-org".to_string(),
+        members: members
+            .iter()
+            .map(|(login, role)| (login.to_string(), *role))
+            .collect::<BTreeMap<_, _>>(),
+    }];
+    common::spawn_app_with(config, github).await
+}
+
+fn remove_mallory(base: &str) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .post(format!("{base}/workspaces/platform/remove"))
+        .bearer_auth(common::TOKEN)
+        .json(&json!({ "items": [{ "repo": "infra", "username": "mallory" }] }))
+}
+
+#[tokio::test]
+async fn viewers_can_audit_and_export_but_not_remove() {
+    let github = github();
+    let base = spawn(github.clone(), &[("Octocat", Role::Viewer)]).await;
+    let client = reqwest::Client::new();
+
+    let memberships: Value = client
+        .get(format!("{base}/workspaces"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        memberships,
+        json!([{ "name": "platform", "org": "octo-org", "role": "viewer" }])
+    );
+
+    let rows: Value = client
+        .get(format!("{base}/workspaces/platform/audit"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rows[0]["repo"]["name"], "infra");
+    assert_eq!(rows[0]["collaborators"][0]["login"], "mallory");
+
+    let export = client
+        .get(format!("{base}/workspaces/platform/export"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(export.status(), StatusCode::OK);
+    assert!(
+        export.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/csv")
+    );
+    assert_eq!(
+        export.text().await.unwrap(),
+        "repo,username,permission,role_name\ninfra,mallory,write,write\n"
+    );
+
+    let removal = remove_mallory(&base).send().await.unwrap();
+    assert_eq!(removal.status(), StatusCode::FORBIDDEN);
+    let members = client
+        .get(format!("{base}/workspaces/platform/members"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(members.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        github.collaborator_logins("octo-org", "infra"),
+        ["octocat", "mallory"]
+    );
+}
+
+#[tokio::test]
+async fn removers_remove_from_the_workspace_org() {
+    let github = github();
+    let base = spawn(github.clone(), &[("octocat", Role::Remover)]).await;
+
+    let response: Value = remove_mallory(&base)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response["success"][0]["username"], "mallory");
+    assert_eq!(github.collaborator_logins("octo-org", "infra"), ["octocat"]);
+}
+
+#[tokio::test]
+async fn non_members_and_unknown_workspaces_are_refused() {
+    let base = spawn(github(), &[("hubot", Role::Admin)]).await;
+    let client = reqwest::Client::new();
+
+    let audit = client
+        .get(format!("{base}/workspaces/platform/audit"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(audit.status(), StatusCode::FORBIDDEN);
+
+    let unknown = client
+        .get(format!("{base}/workspaces/elsewhere/audit"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn outside_collaborators_are_listed_and_removed_org_wide() {
+    let github = github();
+    let base = spawn(github.clone(), &[("octocat", Role::Remover)]).await;
+    let client = reqwest::Client::new();
+
+    let outside: Value = client
+        .get(format!("{base}/workspaces/platform/outside-collaborators"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(outside, json!([{ "login": "mallory", "repos": ["infra"] }]));
+
+    let response: Value = client
+        .post(format!(
+            "{base}/workspaces/platform/outside-collaborators/remove"
+        ))
+        .bearer_auth(common::TOKEN)
+        .json(&json!({ "usernames": ["mallory", "octocat", "hubot"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response["success"],
+        json!([{ "repo": "octo-org", "username": "mallory" }])
+    );
+    assert_eq!(
+        response["failed"][0]["reason"],
+        "cannot remove authenticated user"
+    );
+    assert_eq!(
+        response["failed"][1]["reason"],
+        "user is an organization member, not an outside collaborator"
+    );
+    assert_eq!(github.collaborator_logins("octo-org", "infra"), ["octocat"]);
+}
+
+#[tokio::test]
+async fn teams_are_audited_with_nesting_and_repo_permissions() {
+    let base = spawn(github(), &[("octocat", Role::Viewer)]).await;
+    let client = reqwest::Client::new();
+
+    let teams: Value = client
+        .get(format!("{base}/workspaces/platform/teams"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        teams,
+        json!([
+            {
+                "slug": "engineering",
+                "name": "Engineering",
+                "parent": null,
+                "child_teams": ["sre"],
+                "maintainers": [],
+                "members": ["octocat", "hubot"],
+                "repos": []
+            },
+            {
+                "slug": "sre",
+                "name": "SRE",
+                "parent": "engineering",
+                "child_teams": [],
+                "maintainers": ["octocat"],
+                "members": ["hubot"],
+                "repos": [{ "repo": "infra", "permission": "maintain", "role_name": null }]
+            }
+        ])
+    );
+
+    let page = client
+        .get(format!("{base}/workspaces/platform/teams/view"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("Teams in octo-org"));
+    assert!(page.contains("SRE"));
+    assert!(!page.contains("Remove team access"));
+
+    let removal = client
+        .post(format!(
+            "{base}/workspaces/platform/teams/sre/repos/infra/remove"
+        ))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(removal.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn removers_remove_team_access_and_members() {
+    let base = spawn(github(), &[("octocat", Role::Remover)]).await;
+    let client = reqwest::Client::new();
+    let post = |path: &str| {
+        client
+            .post(format!("{base}/workspaces/platform/teams/{path}/remove"))
+            .bearer_auth(common::TOKEN)
+            .send()
+    };
+
+    assert_eq!(
+        post("sre/repos/infra").await.unwrap().status(),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        post("sre/members/hubot").await.unwrap().status(),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        post("sre/members/hubot").await.unwrap().status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        post("sre/members/octocat").await.unwrap().status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    let teams: Value = client
+        .get(format!("{base}/workspaces/platform/teams"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(teams[1]["members"], json!([]));
+    assert_eq!(teams[1]["maintainers"], json!(["octocat"]));
+    assert_eq!(teams[1]["repos"], json!([]));
+}
+
+#[tokio::test]
+async fn installation_dashboard_is_refused_to_non_admins() {
+    let mut config = common::config();
+    config.github_app = Some(GitHubAppConfig {
+        app_id: 4242,
+        private_key_pem: std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app-key.pem"),
+        )
+        .unwrap(),
+    });
+    config.workspaces = vec![Workspace {
+        name: "platform".to_string(),
+        org: "octo-org".to_string(),
+        members: BTreeMap::from([
+            ("octocat".to_string(), Role::Approver),
+            ("hubot".to_string(), Role::Admin),
+        ]),
+    }];
+    let base = common::spawn_app_with(config, github()).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{base}/app/dashboard"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn team_paths_that_leave_their_segment_are_refused() {
+    let base = spawn(github(), &[("octocat", Role::Remover)]).await;
+    let client = reqwest::Client::new();
+    let post = |path: &str| {
+        client
+            .post(format!("{base}/workspaces/platform/teams/{path}/remove"))
+            .bearer_auth(common::TOKEN)
+            .send()
+    };
+
+    for path in [
+        "x%2F..%2Fsre/members/hubot",
+        "sre/members/x%2F..%2Fhubot",
+        "sre/repos/x%2F..%2Finfra",
+    ] {
+        assert_eq!(
+            post(path).await.unwrap().status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{path}"
+        );
+    }
+
+    let teams: Value = client
+        .get(format!("{base}/workspaces/platform/teams"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(teams[1]["members"], json!(["hubot"]));
+    assert_eq!(teams[1]["repos"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn export_neutralises_formula_cells() {
+    let mut fixture = common::fixture();
+    fixture.repos.push(
+        serde_json::from_value(json!({
+            "id": 10,
+            "name": "infra",
+            "owner": {"login": "octo-org"},
+            "private": true,
+            "archived": false,
+            "fork": false,
+            "collaborators": [
+                {"login": "octocat", "id": 1, "permissions": {"admin": true}, "role_name": "admin"},
+                {"login": "=HYPERLINK(\"x\")", "id": 7, "permissions": {"pull": true}, "role_name": "@read"}
+            ]
+        }))
+        .unwrap(),
+    );
+    let base = spawn(
+        Arc::new(FakeGitHub::new(fixture)),
+        &[("octocat", Role::Viewer)],
+    )
+    .await;
+
+    let export = reqwest::Client::new()
+        .get(format!("{base}/workspaces/platform/export"))
+        .bearer_auth(common::TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        export,
+        "repo,username,permission,role_name\ninfra,\"'=HYPERLINK(\"\"x\"\")\",read,'@read\n"
+    );
+}